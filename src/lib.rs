@@ -0,0 +1,4458 @@
+//! The download → extract → spc2wav → convert → tag pipeline behind the smwc2wav CLI.
+
+use chrono::prelude::DateTime;
+use filetime::FileTime;
+use chrono::Local;
+use chrono::{Datelike, Timelike, Utc};
+use clap::ValueEnum;
+use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressStyle};
+use regex::Regex;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{StatusCode, Url};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use which::which;
+use anyhow::{bail, Context};
+use audiotags::{Album, MimeType, Picture, Tag};
+use inquire::{Confirm, Select, Text};
+use strum::IntoEnumIterator;
+use strum_macros::{AsRefStr, EnumIter};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use std::collections::{HashMap, HashSet};
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{LazyLock, Mutex, OnceLock};
+use std::thread::sleep;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{env, fs, io};
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use regex_macro::regex;
+
+const TINY_CAPS_MAPPING: [char; 26] = ['ᴀ', 'ʙ', 'ᴄ', 'ᴅ', 'ᴇ', 'ғ', 'ɢ', 'ʜ', 'ɪ', 'ᴊ', 'ᴋ', 'ʟ', 'ᴍ', 'ɴ', 'ᴏ', 'ᴘ', 'ꞯ', 'ʀ', 's', 'ᴛ', 'ᴜ', 'ᴠ', 'ᴡ', 'x', 'ʏ', 'ᴢ'];
+const BOLD_MAPPING: [char; 26] = ['𝐚', '𝐛', '𝐜', '𝐝', '𝐞', '𝐟', '𝐠', '𝐡', '𝐢', '𝐣', '𝐤', '𝐥', '𝐦', '𝐧', '𝐨', '𝐩', '𝐪', '𝐫', '𝐬', '𝐭', '𝐮', '𝐯', '𝐰', '𝐱', '𝐲', '𝐳'];
+const BOLD_UPPER_MAPPING: [char; 26] = ['𝐀', '𝐁', '𝐂', '𝐃', '𝐄', '𝐅', '𝐆', '𝐇', '𝐈', '𝐉', '𝐊', '𝐋', '𝐌', '𝐍', '𝐎', '𝐏', '𝐐', '𝐑', '𝐒', '𝐓', '𝐔', '𝐕', '𝐖', '𝐗', '𝐘', '𝐙'];
+const FULLWIDTH_MAPPING: [char; 26] = ['ａ', 'ｂ', 'ｃ', 'ｄ', 'ｅ', 'ｆ', 'ｇ', 'ｈ', 'ｉ', 'ｊ', 'ｋ', 'ｌ', 'ｍ', 'ｎ', 'ｏ', 'ｐ', 'ｑ', 'ｒ', 'ｓ', 'ｔ', 'ｕ', 'ｖ', 'ｗ', 'ｘ', 'ｙ', 'ｚ'];
+const FULLWIDTH_UPPER_MAPPING: [char; 26] = ['Ａ', 'Ｂ', 'Ｃ', 'Ｄ', 'Ｅ', 'Ｆ', 'Ｇ', 'Ｈ', 'Ｉ', 'Ｊ', 'Ｋ', 'Ｌ', 'Ｍ', 'Ｎ', 'Ｏ', 'Ｐ', 'Ｑ', 'Ｒ', 'Ｓ', 'Ｔ', 'Ｕ', 'Ｖ', 'Ｗ', 'Ｘ', 'Ｙ', 'Ｚ'];
+// Plain-ASCII stand-ins for the UI's decorative glyphs (--ascii-ui)
+const ASCII_GLYPHS: [(char, &str); 24] = [
+    ('★', "*"), ('⯨', "+"), ('☆', "-"), ('▶', ">"), ('⏷', "v"), ('✔', "[OK]"), ('✘', "[ERR]"), ('⚠', "!"), ('→', "->"), ('≤', "<="), ('⏭', ">>"),
+    ('█', "#"), ('▓', "#"), ('▒', "="), ('░', "-"),
+    ('╔', "+"), ('╗', "+"), ('╚', "+"), ('╝', "+"), ('═', "="), ('║', "|"), ('└', "`"), ('│', "|"), ('—', "-"),
+];
+// Formats SoX writes out of the box; ffmpeg covers these plus a few lossy containers
+pub const SOX_FORMATS: [&str; 4] = ["flac", "mp3", "aiff", "ogg"];
+pub const FFMPEG_FORMATS: [&str; 7] = ["flac", "mp3", "aiff", "ogg", "m4a", "aac", "opus"];
+// Bytes of a file's start that magic numbers are checked against (the longest signature, SPC's, is 35)
+const MAGIC_HEAD_LEN: usize = 64;
+static IS_ASCII_UI: AtomicBool = AtomicBool::new(false);
+// Status lines print plainly (one per update) rather than redrawing in place, e.g. when stdout isn't a terminal
+static IS_PLAIN_STATUS: AtomicBool = AtomicBool::new(false);
+// Bars, spinners and the phase line all draw through this, so they stack rather than fight over the cursor
+static PROGRESS: LazyLock<MultiProgress> = LazyLock::new(MultiProgress::new);
+// The in-flight phase line (see ow_print), if any
+static PHASE: Mutex<Option<ProgressBar>> = Mutex::new(None);
+/// Pause between consecutive SMWC listing requests while paging through search results.
+const SEARCH_DELAY: Duration = Duration::from_millis(750);
+/// Longest Retry-After (in seconds) s2w_get will wait on before retrying.
+const MAX_RETRY_AFTER: u64 = 60;
+/// Longest of s2w_get's own backoff waits; the doubling stops here.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+static TOOL_PATHS: OnceLock<HashMap<&str, PathBuf>> = OnceLock::new();
+
+#[derive(Clone, Copy, ValueEnum, PartialEq, Debug)]
+pub enum Style {
+    #[value(name = "tinycaps")]
+    TinyCaps,
+    Bold,
+    Fullwidth,
+    Plain
+}
+
+impl Style {
+    /// Character mapper for this stylization (empty for plain, i.e. everything as-is).
+    pub fn mapper(&self) -> HashMap<char, char> {
+        match *self {
+            Style::TinyCaps => alphavec_to_map(TINY_CAPS_MAPPING), // Uppercase stays as-is for that small-caps look
+            Style::Bold => alphavecs_to_map(BOLD_MAPPING, BOLD_UPPER_MAPPING),
+            Style::Fullwidth => alphavecs_to_map(FULLWIDTH_MAPPING, FULLWIDTH_UPPER_MAPPING),
+            Style::Plain => HashMap::new(),
+        }
+    }
+}
+
+/// How a pack track's title reads when its filename is nothing but a number ("03.spc"); the track number is 3 either way.
+#[derive(Clone, Copy, ValueEnum, PartialEq, Debug)]
+pub enum TrackTitleStyle {
+    Raw, // "03", the filename as-is (leading number included, even before a name)
+    Stripped, // "3"
+    Ordinal, // "3rd"
+}
+
+// Based on https://en.wikipedia.org/wiki/Magic_number_(programming)
+#[allow(clippy::upper_case_acronyms)]
+#[derive(AsRefStr, EnumIter, Clone, Copy, PartialEq, Debug)]
+pub enum FileType {
+    JPEG,
+    GIF,
+    PNG,
+    VTF,
+    MIDI,
+    UnixScript,
+    ELF,
+    PDF,
+    MBR,
+    TIFF,
+    WAD,
+    ZIP,
+    TAR,
+    XML,
+    TXT,
+    HEIC,
+    WEBP,
+    NES,
+    BMP,
+    SPC,
+    WAV,
+    AVI,
+    AIFF,
+    AU,
+    MP3,
+    MP4,
+    OGG,
+    FLAC,
+    M4A,
+    AAC
+}
+
+impl FileType {
+    // Returned as a regex pattern with byte values delimited with ; for the sake of generic sizing.
+    // e.g. [4A BC] or [A3 BB 9F] -> (4A;BC|A3;BB;9F)
+    pub fn magic(&self) -> &str {
+        match *self {
+            FileType::JPEG => "(FF;D8;FF;DB|FF;D8;FF;E0;00;10;4A;46;49;46;00;01|FF;D8;FF;EE|FF;D8;FF;E1;([0-9A-F]{2};){2}45;78;69;66;00;00|FF;D8;FF;E0)", // TODO: this excludes JPEG2000, needed?
+            FileType::GIF => "(47;49;46;38;39;61|47;49;46;38;37;61).*", // GIF89a or GIF87a
+            FileType::PNG => "(89;50;4E;47;0D;0A;1A;0A).*", // \211PNG\r\n\032\n
+            FileType::VTF => "(00;46;54;56).*", // VTF\0 (https://developer.valvesoftware.com/wiki/VTF_(Valve_Texture_Format))
+            FileType::MIDI => "(4D;54;68;64).*", // MThd
+            FileType::UnixScript => "(23;21).*", // #!
+            FileType::ELF => "(7F;45;4C;46).*", // 0x7F + ELF
+            FileType::PDF => "(25;50;44;46;2D).*", // %PDF-
+            FileType::MBR => ".*(55;AA)", // 0x55AA
+            FileType::TIFF => "(49;49;2A;00|4D;4D;00;2A|49;49;2B;00|4D;4D;00;2B).*", // II (le) or MM (be) + 0x42
+            FileType::WAD => "(49;57;41;44|50;57;41;44|57;41;44;32|57;41;44;33).*", // IWAD/PWAD (Doom), WAD2 (Quake), WAD3 (Half-Life)
+            FileType::ZIP => "(50;4B;03;04|50;4B;05;06|50;4B;07;08).*", // PK♥♦, PK♣♠ (empty), or PK•◘ (spanned)
+            FileType::TAR => "(75;73;74;61;72;00;30;30|75;73;74;61;72;20;20;00).*", // ustar␀00 or ustar␠␠␀
+            FileType::XML => "(3C;3F;78;6D;6C;20|3C;00;3F;00;78;00;6D;00;6C;00;20|00;3C;00;3F;00;78;00;6D;00;6C;00;20|3C;00;00;00;3F;00;00;00;78;00;00;00;6D;00;00;00;6C;00;00;00;20;00;00;00|00;00;00;3C;00;00;00;3F;00;00;00;78;00;00;00;6D;00;00;00;6C;00;00;00;20).*",
+            FileType::TXT => "(EF;BB;BF|FF;FE|FE;FF|FF;FE;00;00|00;00;FE;FF).*", // ï»¿, ÿþ, þÿ, ÿþ␀␀, or ␀␀þÿ
+            FileType::HEIC => "(([0-9A-F]{2};){4}66;74;79;70;(68;65;69;63|68;65;69;78|6D;69;66;31)).*", // ????ftypheic, ????ftypheix, or ????ftypmif1
+            FileType::WEBP => "(52;49;46;46;([0-9A-F]{2};){4}57;45;42;50).*", // RIFF????WEBP
+            FileType::NES => "(4E;45;53;1A).*", // NES␚
+            FileType::BMP => "(42;4D).*", // BM
+            FileType::SPC => "(53;4E;45;53;2D;53;50;43;37;30;30;20;53;6F;75;6E;64;20;46;69;6C;65;20;44;61;74;61;20;76;30;2E;33;30;1A;1A).*", // SNES-SPC700 Sound File Data v0.30 + 2x 0x26
+            FileType::WAV => "(52;49;46;46;([0-9A-F]{2};){4}57;41;56;45).*", // RIFF????WAVE
+            FileType::AVI => "(52;49;46;46;([0-9A-F]{2};){4}41;56;49;20).*", // RIFF????AVI␠
+            FileType::AIFF => "(46;4F;52;4D;([0-9A-F]{2};){4}41;49;46;46).*", // FORM????AIFF
+            FileType::AU => "(2E;73;6E;64).*", // .snd (Sun/NeXT)
+            FileType::MP3 => "(FF;FB|FF;F3|FF;F2|49;44;33).*", // ÿû, ÿó, or ÿò (or ID3)
+            FileType::MP4 => "(([0-9A-F]{2};){4}66;74;79;70;4D;53;4E;56).*", // ????ftypMSNV
+            FileType::OGG => "(4F;67;67;53).*", // OggS
+            FileType::FLAC => "(66;4C;61;43).*", // fLaC
+            FileType::M4A => "(00;00;00;(1C|20);66;74;79;70;4D;34;41;20).*", // 0x000000 ftypM4A. There was a single byte difference for some reason? Also could also be M4A_? (https://docs.fileformat.com/audio/m4a/)
+            FileType::AAC => "(FF;F1|FF;F9).*", // ÿñ or ÿù
+        }
+    }
+
+    /// Utility method for converting to audiotags::MimeType
+    pub fn mime(&self) -> Result<MimeType, Error> {
+        match *self {
+            FileType::JPEG => Ok(MimeType::Jpeg),
+            FileType::PNG => Ok(MimeType::Png),
+            FileType::TIFF => Ok(MimeType::Tiff),
+            FileType::BMP => Ok(MimeType::Bmp),
+            FileType::GIF => Ok(MimeType::Gif),
+            _ => Err(Error::from(ErrorKind::Unsupported)) // TODO: wrong way to indicate unsupported datatype?
+        }
+    }
+}
+
+/// Resampling/requantizing/downmixing applied while converting; all None passes the audio through as-is.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct AudioOpts {
+    pub rate: Option<u32>,
+    pub bits: Option<u8>,
+    pub dither: Option<Dither>, // Only when bits is below the source's; None leaves it to the encoder
+    pub channels: Option<u8>,
+    pub duration: Option<f64>,
+    pub fade: Option<f64>
+}
+
+/// Dither applied when --bits reduces the source's bit depth.
+#[derive(Clone, Copy, ValueEnum, PartialEq, Debug)]
+pub enum Dither {
+    None,
+    Tpdf, // Triangular (SoX's own default)
+    Shibata, // Noise-shaped
+}
+
+/// User overrides + extras for tagging; all None/false keeps the defaults (submission name, "Game", upload year).
+#[derive(Clone, Default, Debug)]
+pub struct TagOpts {
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    pub year: Option<i32>,
+    pub is_multi_artist: bool,
+    pub is_stats: bool
+}
+
+/// Everything a query's run depends on, resolved (and validated) from the command line up front.
+#[derive(Clone, Debug)]
+pub struct RunOpts {
+    pub output: Option<PathBuf>,
+    pub output_dir: Option<PathBuf>,
+    pub from_wav: Option<PathBuf>,
+    pub spc2wav_outdir: Option<PathBuf>,
+    pub format: Option<String>, // Lowercased target format, or "skip" to keep the WAV; None asks (when interactive)
+    pub is_skip: bool,
+    pub is_format_valid: bool,
+    pub is_interactive: bool,
+    pub is_yes: bool,
+    pub suppress_output: bool, // --quiet, --json or --summary-only: no banner, step lines or progress
+    pub quiet: bool,
+    pub json: bool,
+    pub no_progress: bool,
+    pub no_delay: bool,
+    pub dry_run: bool,
+    pub download_only: bool,
+    pub list_tracks: bool,
+    pub preview_description: bool,
+    pub no_convert: bool,
+    pub no_cache: bool,
+    pub force: bool, // Render every track again, even one already rendered into the output directory
+    pub keep_dirs: bool,
+    pub ascii_filenames: bool,
+    pub open_folder: bool,
+    pub preserve_time: bool,
+    pub auto_cover: bool,
+    pub dedup_authors: bool,
+    pub coverart: Option<String>, // Where cover came from, for --dry-run
+    pub cover: Option<(Vec<u8>, MimeType)>,
+    pub style: Style,
+    pub track_title_style: TrackTitleStyle,
+    pub album_mode: Option<bool>, // --album-mode/--no-album-mode; None goes by SPC count (albums are packs)
+    pub require_tag: Vec<String>,
+    pub exclude_tag: Vec<String>,
+    pub keep_ext: Vec<String>,
+    pub trim_start: Option<f64>,
+    pub peak_normalize_to: Option<f64>,
+    pub audio: AudioOpts,
+    pub tags: TagOpts,
+    pub jobs: usize,
+    pub retries: u32,
+}
+
+/// Which tool turns the WAVs into the target format.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Encoder {
+    Sox,
+    Ffmpeg
+}
+
+impl Encoder {
+    /// Prefers SoX, falling back to ffmpeg for formats SoX can't write or when SoX isn't installed.
+    pub fn pick(format: &str, has_sox: bool, has_ffmpeg: bool) -> Option<Encoder> {
+        if has_sox && SOX_FORMATS.contains(&format) {
+            Some(Encoder::Sox)
+        } else if has_ffmpeg && FFMPEG_FORMATS.contains(&format) {
+            Some(Encoder::Ffmpeg)
+        } else {
+            None
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match *self {
+            Encoder::Sox => "SoX",
+            Encoder::Ffmpeg => "ffmpeg",
+        }
+    }
+}
+
+/// Every FileType's magic, compiled once and anchored to the start of its window; a malformed pattern panics on first detection, whichever type is looked up.
+static MAGIC_REGEXES: LazyLock<Vec<(FileType, Regex)>> = LazyLock::new(|| FileType::iter()
+    .map(|f| {
+        let re = Regex::new(&format!("^(?:{})", f.magic())).unwrap_or_else(|e| panic!("Invalid magic pattern for {}: {}", f.as_ref(), e));
+        (f, re)
+    })
+    .collect());
+
+
+#[allow(dead_code)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct SMWCUser {
+    pub id: u16,
+    pub name: String
+}
+
+#[allow(dead_code)]
+#[derive(Serialize, Deserialize, Debug, Default, JsonSchema)]
+pub struct SMWCAudioFields {
+    #[serde(default, deserialize_with = "de_unistr")]
+    pub size: String,
+    #[serde(rename = "type", default, deserialize_with = "de_unistr")]
+    pub variant: String,
+    #[serde(default, deserialize_with = "de_unistr")]
+    pub samples: String,
+    #[serde(default, deserialize_with = "de_unistr")]
+    pub source: String,
+    #[serde(default, deserialize_with = "de_unistr")]
+    pub duration: String,
+    #[serde(default, deserialize_with = "de_flag")]
+    pub featured: bool,
+    #[serde(default, deserialize_with = "de_unistr")]
+    pub description: String
+}
+
+/// What a submission's "samples" field says about its instruments.
+#[derive(Debug, PartialEq)]
+enum SampleUse {
+    Vanilla,
+    Custom(Option<String>), // Named sample pack, if given
+    Unknown(String),
+}
+
+impl SampleUse {
+    /// Reads SMWC's conventions for the field: "No"/"None" (vanilla), "Yes" (custom), or a sample pack name.
+    pub fn parse(raw: &str) -> SampleUse {
+        let raw = raw.trim();
+        match raw.to_ascii_lowercase().as_str() {
+            "" | "?" | "n/a" | "unknown" => SampleUse::Unknown(raw.to_string()),
+            "no" | "none" | "false" | "vanilla" | "default" | "original" => SampleUse::Vanilla,
+            "yes" | "true" | "custom" => SampleUse::Custom(None),
+            _ => SampleUse::Custom(Some(raw.to_string())),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            SampleUse::Vanilla => "vanilla (SMW's own)".to_string(),
+            SampleUse::Custom(None) => "custom".to_string(),
+            SampleUse::Custom(Some(pack)) => format!("custom ({})", pack),
+            SampleUse::Unknown(raw) if raw.is_empty() => "(not given)".to_string(),
+            SampleUse::Unknown(raw) => raw.clone(),
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SMWCFile {
+    pub id: u16,
+    pub section: String,
+    pub name: String,
+    pub time: u64,
+    #[serde(deserialize_with = "de_authors")]
+    pub authors: Vec<SMWCUser>,
+    pub submitter: Option<SMWCUser>,
+    pub tags: Vec<String>,
+    pub images: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "de_rating")]
+    pub rating: Option<f32>,
+    pub size: u32,
+    pub downloads: u16,
+    pub download_url: String,
+    pub obsoleted_by: Option<u16>,
+    // fields: SMWCAudioFields, <- unnecessary
+    #[serde(default)]
+    pub raw_fields: SMWCAudioFields
+}
+
+/// What ajax.php?a=getfile hands back: the file itself, or an error object for bad/removed IDs.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum SMWCResponse {
+    File(Box<SMWCFile>),
+    ApiError { error: String }
+}
+
+/// One entry of ajax.php?a=getsectionlist; listings carry less than getfile, so everything past the ID is lenient.
+#[derive(Debug, Deserialize)]
+struct SMWCListing {
+    pub id: u16,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, deserialize_with = "de_authors")]
+    pub authors: Vec<SMWCUser>,
+    #[serde(default, deserialize_with = "de_rating")]
+    pub rating: Option<f32>,
+    #[serde(default)]
+    pub downloads: u16,
+}
+
+/// A page of section listing results.
+#[derive(Debug, Deserialize)]
+struct SMWCPage {
+    pub data: Vec<SMWCListing>,
+    #[serde(default)]
+    pub last_page: u32,
+    #[serde(default)]
+    pub total: u32,
+}
+
+/// Durable run log for --log-file; every call is a no-op when no file was requested.
+pub struct RunLog {
+    pub file: Option<fs::File>
+}
+
+impl RunLog {
+    /// Opens (rotating or appending to) the log file, recording panics into it as well.
+    pub fn open(loc: Option<&Path>, is_append: bool) -> anyhow::Result<RunLog> {
+        let Some(loc) = loc else {
+            return Ok(RunLog { file: None });
+        };
+
+        if !is_append && loc.exists() {
+            let mut rotated = loc.as_os_str().to_owned();
+            rotated.push(".old");
+            fs::rename(loc, rotated).with_context(|| format!("could not rotate previous log file {}", loc.display()))?;
+        }
+
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(loc)
+            .with_context(|| format!("could not open log file {}", loc.display()))?;
+
+        // Panics are how most failures surface, so make sure they land in the log too
+        let hook_file = file.try_clone().with_context(|| format!("could not open log file {}", loc.display()))?;
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let _ = writeln!(&hook_file, "[{}] PANIC {}", Local::now().format("%Y-%m-%d %H:%M:%S"), info);
+            default_hook(info);
+        }));
+
+        Ok(RunLog { file: Some(file) })
+    }
+
+    pub fn log(&self, msg: &str) {
+        if let Some(mut file) = self.file.as_ref() {
+            let _ = writeln!(file, "[{}] {}", Local::now().format("%Y-%m-%d %H:%M:%S"), msg);
+        }
+    }
+
+    /// Logs a finished subprocess along with its exit status and captured output.
+    pub fn log_output(&self, cmd: &Command, out: &Output) {
+        self.log(&format!("$ {:?} → {}", cmd, out.status));
+
+        for (name, stream) in [("stdout", &out.stdout), ("stderr", &out.stderr)] {
+            let text = String::from_utf8_lossy(stream);
+            if !text.trim().is_empty() {
+                self.log(&format!("{}:\n{}", name, text.trim_end()));
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default, JsonSchema)]
+pub struct ID666 {
+    pub song: String,
+    pub game: String,
+    pub artist: String,
+    pub dumper: String,
+    pub comments: String,
+    pub dumped: String,
+    pub length: Option<u32>, // Seconds to play before fading out
+    pub fade: Option<u32>, // Fade length in ms
+    pub emulator: String,
+
+    // Only available via the extended (xid6) chunk
+    pub ost: String, // Official soundtrack title
+    pub disc: Option<u8>,
+    pub track: Option<u8>,
+    pub publisher: String,
+    pub copyright_year: Option<u16>,
+    pub intro_length: Option<f64>, // Seconds
+    pub loop_length: Option<f64>, // Seconds
+}
+
+/// --json result of one query: the submission as SMWC describes it, plus every file written.
+#[derive(Serialize, Debug, JsonSchema)]
+pub struct RunResult {
+    pub file: SMWCFile,
+    pub outputs: Vec<String>
+}
+
+/// How a query's run ended, short of failing.
+#[derive(Debug)]
+pub enum RunOutcome {
+    Saved(Box<RunResult>),
+    Skipped { id: u16, name: String, why: String }, // Filtered out by --require-tag/--exclude-tag
+    Planned(DryRunPlan), // --dry-run: what would happen, nothing fetched or written
+    Shown, // Description preview or track listing: nothing written
+}
+
+/// --dry-run (and its --json form): what a query would do, as far as it's known without fetching the submission.
+#[derive(Serialize, Debug, JsonSchema)]
+pub struct DryRunPlan {
+    pub id: u16,
+    pub url: String,
+    pub output: String,
+    pub format: String,
+    pub album: String,
+    pub cover: String,
+    pub stats: bool,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    Saved,
+    Skipped,
+    Shown,
+    Failed,
+    Panicked,
+}
+
+/// One batch item's line in the --summary-only table (and its --json form).
+#[derive(Serialize, Debug, JsonSchema)]
+pub struct RunReport {
+    pub query: String,
+    pub id: Option<u16>,
+    pub name: Option<String>,
+    pub status: RunStatus,
+    pub outputs: Vec<String>,
+    pub bytes: u64, // Total size of the outputs
+    pub error: Option<String>, // Why it was skipped or failed
+}
+
+impl RunReport {
+    pub fn new(query: &str, res: &anyhow::Result<RunOutcome>) -> RunReport {
+        let mut report = RunReport { query: query.to_string(), id: None, name: None, status: RunStatus::Shown, outputs: vec![], bytes: 0, error: None };
+        match res {
+            Ok(RunOutcome::Saved(r)) => {
+                report.id = Some(r.file.id);
+                report.name = Some(r.file.name.clone());
+                report.status = RunStatus::Saved;
+                report.outputs = r.outputs.clone();
+                report.bytes = r.outputs.iter().filter_map(|o| fs::metadata(o).ok()).map(|m| m.len()).sum();
+            }
+            Ok(RunOutcome::Skipped { id, name, why }) => {
+                report.id = Some(*id);
+                report.name = Some(name.clone());
+                report.status = RunStatus::Skipped;
+                report.error = Some(why.clone());
+            }
+            Ok(RunOutcome::Planned(plan)) => report.id = Some(plan.id),
+            Ok(RunOutcome::Shown) => report.id = smwc_id(query).and_then(|id| id.parse().ok()),
+            Err(e) => {
+                report.id = smwc_id(query).and_then(|id| id.parse().ok());
+                report.status = RunStatus::Failed;
+                report.error = Some(format!("{:#}", e));
+            }
+        }
+        report
+    }
+
+    /// Report for a query whose run panicked instead of returning.
+    pub fn panicked(query: &str, msg: &str) -> RunReport {
+        RunReport { query: query.to_string(), id: smwc_id(query).and_then(|id| id.parse().ok()), name: None, status: RunStatus::Panicked, outputs: vec![], bytes: 0, error: Some(msg.to_string()) }
+    }
+}
+
+/// Widest a skip/failure reason gets in the --summary-only table.
+const SUMMARY_ERROR_WIDTH: usize = 60;
+
+/// Conversion format implied by an --output path's extension ("skip" for .wav, which spc2wav already produces).
+pub fn output_format(output: &Path) -> anyhow::Result<String> {
+    let ext = output.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .context("Output path has no extension to infer format from!")?;
+
+    Ok(match ext.as_str() {
+        "wav" => "skip".to_string(),
+        e if FFMPEG_FORMATS.contains(&e) => ext,
+        _ => bail!("Invalid output extension! Must be wav/{}", FFMPEG_FORMATS.join("/")),
+    })
+}
+
+/// JSON Schemas of the API response and every --json output structure, keyed by type name.
+pub fn json_schemas() -> Value {
+    serde_json::json!({
+        "SMWCFile": schemars::schema_for!(SMWCFile),
+        "SPCHeader": schemars::schema_for!(SPCHeader),
+        "Identified": schemars::schema_for!(Identified),
+        "RunResult": schemars::schema_for!(RunResult),
+        "DryRunPlan": schemars::schema_for!(DryRunPlan),
+        "RunReport": schemars::schema_for!(RunReport),
+        "CacheEntry": schemars::schema_for!(CacheEntry),
+    })
+}
+
+/// Lays batch reports out as an aligned ID/NAME/STATUS/OUTPUT/SIZE table (packs show their first output + a count).
+pub fn summary_table(reports: &[RunReport]) -> String {
+    let mut rows = vec![["ID".to_string(), "NAME".to_string(), "STATUS".to_string(), "OUTPUT".to_string(), "SIZE".to_string()]];
+    for r in reports {
+        let output = match (r.outputs.first(), r.outputs.len()) {
+            (Some(o), 1) => o.clone(),
+            (Some(o), n) => format!("{} (+{} more)", o, n - 1),
+            // Errors carry their whole context chain; the table only has room for the start of it
+            (None, _) => match &r.error {
+                Some(e) if e.width() > SUMMARY_ERROR_WIDTH => {
+                    let mut w = 0;
+                    let cut: String = e.chars().take_while(|c| { w += c.width().unwrap_or(0); w < SUMMARY_ERROR_WIDTH }).collect();
+                    format!("{}…", cut)
+                }
+                Some(e) => e.clone(),
+                None => "-".to_string(),
+            },
+        };
+        rows.push([
+            r.id.map_or_else(|| r.query.clone(), |id| id.to_string()),
+            r.name.clone().unwrap_or_else(|| "-".to_string()),
+            serde_json::to_value(r.status).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default(),
+            output,
+            if r.outputs.is_empty() { "-".to_string() } else { HumanBytes(r.bytes).to_string() },
+        ]);
+    }
+
+    let widths: Vec<usize> = (0..5).map(|c| rows.iter().map(|r| r[c].width()).max().unwrap_or(0)).collect();
+    rows.iter()
+        .map(|r| r.iter().zip(&widths).enumerate()
+            .map(|(c, (cell, w))| if c == 4 { cell.clone() } else { format!("{}{}", cell, " ".repeat(w - cell.width())) })
+            .collect::<Vec<String>>()
+            .join("  "))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[derive(Serialize, Debug, JsonSchema)]
+pub struct Identified {
+    #[serde(serialize_with = "ser_lossy_path")]
+    #[schemars(with = "String")]
+    pub path: PathBuf,
+    #[serde(rename = "type")]
+    pub variant: Option<String>,
+    pub prefix: Option<String>, // Hex of the first 16 bytes; only reported for unidentified files
+}
+
+/// One submission held in the download cache (list-cached); either half may be missing if a store was interrupted.
+#[derive(Serialize, Debug, PartialEq, JsonSchema)]
+pub struct CacheEntry {
+    pub id: u16,
+    pub name: Option<String>, // From the cached metadata
+    pub cached: Option<u64>, // Unix time the entry was stored
+    pub metadata_bytes: Option<u64>,
+    pub zip_bytes: Option<u64>,
+}
+
+#[derive(Serialize, Debug, JsonSchema)]
+pub struct SPCHeader {
+    pub version: String,
+    pub id666_format: Option<String>, // "text" or "binary"; None if the header states there is no ID666 tag
+    pub has_xid6: bool,
+    pub id666: Option<ID666>,
+}
+
+
+/// Serde JSON deserialiser to capture anything as a string; null becomes "" (* -> String)
+fn de_unistr<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    // Deserialize into blanket serde_json::Value then case-match
+    let value = Value::deserialize(deserializer)?;
+
+    Ok(match value {
+        Value::Null => String::new(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s,
+        Value::Array(arr) => format!("{:?}", arr),
+        Value::Object(o) => format!("{:?}", o),
+    })
+}
+
+/// Serde JSON deserialiser for ratings sent as a number, a numeric string or null; unusable values become None (* -> Option<f32>)
+fn de_rating<'de, D>(deserializer: D) -> Result<Option<f32>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Value::deserialize(deserializer)?;
+
+    let r = match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse::<f64>().ok(),
+        _ => None,
+    };
+    Ok(r.filter(|r| r.is_finite() && *r >= 0.0).map(|r| r as f32))
+}
+
+/// Serde JSON deserialiser for flags sent as a bool, 0/1 or a yes/no-ish string; anything else is false (* -> bool)
+fn de_flag<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Value::deserialize(deserializer)?;
+
+    Ok(match value {
+        Value::Bool(b) => b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => matches!(s.trim().to_lowercase().as_str(), "1" | "true" | "yes" | "y"),
+        _ => false,
+    })
+}
+
+/// Serde JSON deserialiser to capture authors as either user objects or a comma-delimited string (* -> Vec<SMWCUser>)
+fn de_authors<'de, D>(deserializer: D) -> Result<Vec<SMWCUser>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    // Older/alternate API responses flatten authors to "A, B, C"; those have no IDs, so use 0
+    let value = Value::deserialize(deserializer)?;
+
+    Ok(match value {
+        Value::String(s) => s
+            .split(',')
+            .map(str::trim)
+            .filter(|n| !n.is_empty())
+            .map(|n| SMWCUser { id: 0, name: n.to_string() })
+            .collect(),
+        Value::Null => vec![],
+        v => serde_json::from_value(v).map_err(serde::de::Error::custom)?,
+    })
+}
+
+/// Names to credit a submission to: its authors, else whoever submitted it, else "Unknown".
+fn author_names(authors: &[SMWCUser], submitter: Option<&SMWCUser>) -> Vec<String> {
+    let names: Vec<String> = authors.iter()
+        .map(|a| a.name.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .collect();
+    if !names.is_empty() {
+        return names;
+    }
+
+    match submitter.map(|s| s.name.trim()).filter(|n| !n.is_empty()) {
+        Some(n) => vec![n.to_string()],
+        None => vec!["Unknown".to_string()],
+    }
+}
+
+/// Stand-in for a submission's name when it's blank (or only whitespace), None if it has a real one.
+fn placeholder_name(file: &SMWCFile) -> Option<String> {
+    file.name.trim().is_empty().then(|| format!("(untitled #{})", file.id))
+}
+
+/// Drops repeated author names (case-insensitive), keeping each one's first appearance in order.
+fn dedup_authors(authors: &mut Vec<SMWCUser>) {
+    let mut seen = HashSet::new();
+    authors.retain(|a| seen.insert(a.name.to_lowercase()));
+}
+
+/// Serde serialiser for paths that aren't valid UTF-8 (which would otherwise fail mid-document) (PathBuf -> String)
+fn ser_lossy_path<S>(path: &Path, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.serialize_str(&path.to_string_lossy())
+}
+
+/// Writes one JSON document to stdout and nothing else, so output stays pipeable (e.g. into jq). A closed pipe isn't an error.
+pub fn print_json<T: Serialize>(value: &T) {
+    write_json(value, true);
+}
+
+/// Like print_json, but compact on a single line (JSON Lines), for batches where each item is its own document.
+pub fn print_json_line<T: Serialize>(value: &T) {
+    write_json(value, false);
+}
+
+fn write_json<T: Serialize>(value: &T, pretty: bool) {
+    let mut stdout = io::stdout().lock();
+    let written = if pretty { serde_json::to_writer_pretty(&mut stdout, value) } else { serde_json::to_writer(&mut stdout, value) };
+    let result = written
+        .map_err(Error::from)
+        .and_then(|_| writeln!(stdout))
+        .and_then(|_| stdout.flush());
+
+    match result {
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => {}
+        r => r.expect("Could not write JSON to stdout"),
+    }
+}
+
+/// Parses Unix time to human-readable time object + timezone difference.
+fn unix_to_hrtime(unix_off: u64) -> DateTime<Utc> {
+    let tz_off = Local::now().offset().local_minus_utc();
+    let unix_time = UNIX_EPOCH + Duration::from_secs((unix_off as i64 + tz_off as i64) as u64);
+    DateTime::<Utc>::from(unix_time)
+}
+
+/// Renders a 0–5 rating as stars, to the nearest half star; out-of-range ratings are clamped.
+fn rating_str(rating: f32) -> String {
+    let halves = (rating.clamp(0.0, 5.0) * 2.0).round() as u8;
+    let mut res = "★".repeat((halves / 2) as usize);
+    if halves % 2 == 1 {
+        res.push('⯨');
+    }
+    res.push_str(&"☆".repeat(5 - halves.div_ceil(2) as usize));
+    res
+}
+
+/// Strips "HTML" (html string) to its string equivalent.
+/// <br> (and any newline right after it) → \n, other tags dropped, entities decoded, and runs of blank lines collapsed to one.
+fn strip_html(h_str: &str) -> String {
+    let res = h_str.replace("\r\n", "\n").replace('\r', "\n");
+    let res = regex!(r"(?i)<br\s*/?>\n?").replace_all(&res, "\n");
+    let res = regex!(r"<[^>]*>").replace_all(&res, "");
+
+    // One pass, so "&amp;lt;" stays "&lt;" rather than decoding twice
+    let res = regex!(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z]+);").replace_all(&res, |c: &regex::Captures| {
+        let ent = &c[1];
+        let decoded = match ent {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ if ent.starts_with("#x") || ent.starts_with("#X") => u32::from_str_radix(&ent[2..], 16).ok().and_then(char::from_u32),
+            _ if ent.starts_with('#') => ent[1..].parse().ok().and_then(char::from_u32),
+            _ => None,
+        };
+        decoded.map_or(c[0].to_string(), |ch| ch.to_string())
+    });
+
+    let res = regex!(r"[ \t]+\n").replace_all(&res, "\n");
+    regex!(r"\n{3,}").replace_all(res.trim(), "\n\n").into()
+}
+
+/// Prints the decorative submission banner (title box, metadata tree, description). Specific to this project (s2w).
+fn s2w_banner(file: &SMWCFile, style: Style) {
+    let is_featured = file.raw_fields.featured;
+
+    let hrtime = unix_to_hrtime(file.time);
+    let alphamapper = style.mapper();
+    let sty = |s: &str| alphamap(s, &alphamapper);
+    let title = ui(&sty(&file.name));
+
+    println!("{}", ui("\x1B[38;2;131;125;246m\n▓▓▓▓▓▓▓▓▓▒▓▓▓▓▒▒▒▒▒▒▓▓▒▒▓▒▒▒▒▒░▒▒▒▒▒▒▒▒░▒▒░░▒▒▒▒▒░░░▒▒░▒░▒▒░░▒▒▒▒▒░▒▒░░░░▒▒▒░░░░░▒░▒░░░░░▒░░░░░▒░░░░░░░░░░░░░\n"));
+
+    println!("{}", ui(&format!(" ╔{}╗", "═".repeat(title.width() + 2 + if is_featured { 2 } else { 0 }))));
+    print!("{}", ui(&format!(" ║ {}", title)));
+    if is_featured { println!("{}", ui(" * ║")) } else { println!("{}", ui("  ║")) }
+    println!("{}", ui(&format!(" ╚{}╝", "═".repeat(title.width() + 2 + if is_featured { 2 } else { 0 }))));
+
+    print!("{}", ui(&format!("  └── {} ——— {} ——— {}", file.raw_fields.duration, HumanBytes(file.size as u64), author_names(&file.authors, file.submitter.as_ref()).join(", "))));
+    match &file.submitter {
+        Some(s) => println!("[{}]", ui(&s.name)),
+        None => println!()
+    }
+
+    println!("{}", ui(&format!("     │\n     └── @ {}-{}-{} {}:{}:{}", hrtime.month(), hrtime.day(), hrtime.year(), hrtime.hour(), hrtime.minute(), hrtime.second())));
+
+    match file.rating {
+        Some(r) => print!("{}", ui(&format!("        │\n        └── {}", rating_str(r)))),
+        None => print!("{}", ui("        │\n        └── (no rating)"))
+    }
+    println!(" {} downloads\n\n", file.downloads);
+
+    println!("{}", ui(&format!("{} ▶  {}", sty("tags"), sty(&file.tags.join(", ")))));
+    println!("{}", ui(&format!("{} ▶  {}", sty("source"), file.raw_fields.source)));
+    println!("{}", ui(&format!("{} ▶  {}\n\n\n\n", sty("samples"), SampleUse::parse(&file.raw_fields.samples).describe())));
+    println!("{}", ui(&format!("⏷  {}  ⏷\n\n{}", sty("info"), s2w_description(file))));
+    println!("{}", ui("\n▓▓▓▓▓▓▓▓▓▒▓▓▓▓▒▒▒▒▒▒▓▓▒▒▓▒▒▒▒▒░▒▒▒▒▒▒▒▒░▒▒░░▒▒▒▒▒░░░▒▒░▒░▒▒░░▒▒▒▒▒░▒▒░░░░▒▒▒░░░░░▒░▒░░░░░▒░░░░░▒░░░░░░░░░░░░░\x1B[0m\n"));
+}
+
+/// Why a submission's tags fail --require-tag/--exclude-tag, if they do.
+fn tag_mismatch(tags: &[String], required: &[String], excluded: &[String]) -> Option<String> {
+    let has = |want: &String| tags.iter().any(|t| t.trim().eq_ignore_ascii_case(want.trim()));
+
+    let missing: Vec<&str> = required.iter().filter(|t| !has(t)).map(|t| t.trim()).collect();
+    if !missing.is_empty() {
+        return Some(format!("not tagged {}", missing.join(", ")));
+    }
+
+    let hit: Vec<&str> = excluded.iter().filter(|t| has(t)).map(|t| t.trim()).collect();
+    (!hit.is_empty()).then(|| format!("tagged {}", hit.join(", ")))
+}
+
+/// Cleans + wraps a submission's description for display.
+fn s2w_description(file: &SMWCFile) -> String {
+    strclamp(&strip_html(&file.raw_fields.description), 100)
+}
+
+/// Converts alphabetical vector of mappings to map.
+fn alphavec_to_map(alphavec: [char; 26]) -> HashMap<char, char> {
+    ('a'..='z').zip(alphavec.iter().copied()).collect()
+}
+
+/// Converts alphabetical vectors of lowercase and uppercase mappings to a single map.
+fn alphavecs_to_map(alphavec: [char; 26], upper_alphavec: [char; 26]) -> HashMap<char, char> {
+    let mut res = alphavec_to_map(alphavec);
+    res.extend(('A'..='Z').zip(upper_alphavec.iter().copied()));
+    res
+}
+
+/// Clamps string to specified max length (display columns) per line, wrapping at spaces where possible; every line ends in \n.
+fn strclamp(str: &str, max_len: usize) -> String {
+    let max_len = max_len.max(1);
+    let mut res = String::with_capacity(str.len() + str.len() / max_len + 1);
+
+    for para in str.split('\n') {
+        let mut line = String::new();
+        let mut line_w = 0;
+
+        for word in para.split(' ') {
+            let word_w = word.width();
+            if line_w > 0 && line_w + 1 + word_w > max_len {
+                res.push_str(&line);
+                res.push('\n');
+                line.clear();
+                line_w = 0;
+            }
+            if line_w > 0 {
+                line.push(' ');
+                line_w += 1;
+            }
+
+            // Words wider than a whole line get broken between chars instead
+            for c in word.chars() {
+                let c_w = c.width().unwrap_or(0);
+                if line_w + c_w > max_len && line_w > 0 {
+                    res.push_str(&line);
+                    res.push('\n');
+                    line.clear();
+                    line_w = 0;
+                }
+                line.push(c);
+                line_w += c_w;
+            }
+        }
+
+        res.push_str(&line);
+        res.push('\n');
+    }
+
+    res
+}
+
+/// Applies the provided character mapper, leaving as-is non-defined mappings.
+fn alphamap(str: &str, mapper: &HashMap<char, char>) -> String {
+    str
+        .chars()
+        .map(|c| mapper.get(&c).copied().unwrap_or(c))
+        .collect()
+}
+
+/// Creates a progress bar of the specified length, drawing nothing if progress is hidden (--no-progress).
+fn s2w_bar(len: u64, is_hidden: bool) -> ProgressBar {
+    if is_hidden { ProgressBar::hidden() } else { PROGRESS.add(ProgressBar::new(len)) }
+}
+
+/// Creates a spinner for steps of unknown length, drawing nothing if progress is hidden (--no-progress).
+fn s2w_spinner(msg: &str, is_hidden: bool) -> ProgressBar {
+    let spinner = if is_hidden { ProgressBar::hidden() } else { PROGRESS.add(ProgressBar::new_spinner()) }
+        .with_style(ProgressStyle::with_template("{spinner} {msg}").unwrap().tick_chars(tick_chars()))
+        .with_message(msg.to_string());
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    spinner
+}
+
+/// The SMWC API endpoint for a submission's metadata.
+fn api_url(id: &str) -> String {
+    format!("{}/ajax.php?a=getfile&v=2&id={}", api_root(), id)
+}
+
+/// Where the SMWC API lives; tests point it at a local mock server instead (per thread, so they can run side by side).
+fn api_root() -> String {
+    #[cfg(test)]
+    if let Some(root) = tests::API_ROOT.with(|r| r.borrow().clone()) {
+        return root;
+    }
+    "https://www.smwcentral.net".to_string()
+}
+
+/// Fetches a submission's metadata from the SMWC API. Specific to this project (s2w).
+fn s2w_fetch(id: &str, client: &reqwest::blocking::Client, retries: u32, log: &RunLog, no_progress: bool) -> anyhow::Result<SMWCFile> {
+    let smwc_api = Url::parse(&api_url(id)).context("invalid SMWc API URL")?;
+    log.log(&format!("GET {}", smwc_api));
+
+    let spinner = s2w_spinner("Fetching metadata…", no_progress);
+    let api_resp = s2w_get(client, smwc_api.as_str(), retries, log).with_context(|| format!("could not connect to SMWCentral for id {}", id));
+    let api_resp = api_resp.and_then(|r| {
+        let status = r.status();
+        Ok((status, r.bytes().with_context(|| format!("could not read SMWC API response for id {}", id))?))
+    });
+    spinner.finish_and_clear();
+
+    let (api_status, api_body) = api_resp?;
+    log.log(&format!("Response {}", api_status));
+    let api_body = gunzip_lenient(&api_body);
+
+    // Outages, rate limits and bad IDs tend to come back as an HTML page rather than JSON
+    if api_body.trim_ascii_start().starts_with(b"<") {
+        bail!("SMWCentral returned an HTML page instead of JSON for id {} (HTTP {}); is the ID valid and the site up?", id, api_status);
+    }
+    let file = match serde_json::from_slice::<SMWCResponse>(&api_body) {
+        Ok(SMWCResponse::File(f)) if f.section == "smwmusic" => f,
+        Ok(SMWCResponse::File(f)) => bail!("smwc2wav only converts Music-section SPC submissions, got section '{}' (file {})", f.section, id),
+        Ok(SMWCResponse::ApiError { error }) => bail!("SMWC file {} not found or not a Music submission: {}", id, error),
+        Err(e) if !api_status.is_success() => bail!("SMWC file {} not found or not a Music submission (HTTP {}): {}", id, api_status, e),
+        Err(e) => return Err(e).with_context(|| format!("failed to parse SMWC API response for id {} (HTTP {})", id, api_status)),
+    };
+
+    Ok(*file)
+}
+
+/// Where downloaded zips (+ the metadata they were fetched under) are kept between runs: $XDG_CACHE_HOME/smwc2wav, else ~/.cache/smwc2wav
+/// (%LOCALAPPDATA%\smwc2wav on Windows).
+fn cache_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CACHE_HOME").filter(|d| !d.is_empty()).map(PathBuf::from);
+
+    #[cfg(windows)]
+    let base = base.or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from));
+    #[cfg(not(windows))]
+    let base = base.or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")));
+
+    base.map(|d| d.join("smwc2wav"))
+}
+
+/// Path of a cached zip, if there's one still current for this file (same upload time + size as the API now reports). Specific to this project (s2w).
+fn s2w_cache_hit(file: &SMWCFile) -> Option<PathBuf> {
+    let dir = cache_dir()?;
+    let zip = dir.join(format!("{}.zip", file.id));
+    let cached: SMWCFile = serde_json::from_slice(&fs::read(dir.join(format!("{}.json", file.id))).ok()?).ok()?;
+    let zip_size = fs::metadata(&zip).ok()?.len();
+
+    (cached.time == file.time && (file.size == 0 || zip_size == file.size as u64)).then_some(zip)
+}
+
+/// Stores a freshly downloaded zip + its metadata in the cache. Specific to this project (s2w).
+fn s2w_cache_store(file: &SMWCFile, zip: &Path) -> anyhow::Result<()> {
+    let dir = cache_dir().context("no cache directory (neither XDG_CACHE_HOME nor HOME/LOCALAPPDATA is set)")?;
+    fs::create_dir_all(&dir).with_context(|| format!("could not create {}", dir.display()))?;
+    fs::copy(zip, dir.join(format!("{}.zip", file.id))).with_context(|| format!("could not cache {}", zip.display()))?;
+    fs::write(dir.join(format!("{}.json", file.id)), serde_json::to_vec(file)?).context("could not cache file metadata")?;
+    Ok(())
+}
+
+/// Reads what's in a cache directory: every <id>.json/<id>.zip pair (or lone half), by ID. Anything else is ignored.
+fn cache_entries(dir: &Path) -> anyhow::Result<Vec<CacheEntry>> {
+    let mut entries: HashMap<u16, CacheEntry> = HashMap::new();
+    for dirent in fs::read_dir(dir).with_context(|| format!("could not read {}", dir.display()))? {
+        let path = dirent?.path();
+        let (Some(id), Some(ext)) = (path.file_stem().and_then(|s| s.to_str()?.parse::<u16>().ok()), path.extension().and_then(|e| e.to_str())) else {
+            continue;
+        };
+        let Ok(meta) = fs::metadata(&path) else { continue };
+        let entry = entries.entry(id).or_insert(CacheEntry { id, name: None, cached: None, metadata_bytes: None, zip_bytes: None });
+
+        match ext {
+            "json" => {
+                entry.metadata_bytes = Some(meta.len());
+                entry.name = fs::read(&path).ok().and_then(|d| serde_json::from_slice::<SMWCFile>(&d).ok()).map(|f| f.name);
+            }
+            "zip" => entry.zip_bytes = Some(meta.len()),
+            _ => continue,
+        }
+        let stored = meta.modified().ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_secs());
+        entry.cached = entry.cached.max(stored);
+    }
+
+    let mut entries: Vec<CacheEntry> = entries.into_values().filter(|e| e.metadata_bytes.is_some() || e.zip_bytes.is_some()).collect();
+    entries.sort_by_key(|e| e.id);
+    Ok(entries)
+}
+
+/// Prints what's in the cache directory (only the given IDs, if any). Specific to this project (s2w).
+pub fn s2w_list_cached(ids: &[u16], is_json: bool) -> anyhow::Result<()> {
+    let dir = cache_dir().context("no cache directory (neither XDG_CACHE_HOME nor HOME/LOCALAPPDATA is set)")?;
+    let mut entries = if dir.exists() { cache_entries(&dir)? } else { vec![] };
+    if !ids.is_empty() {
+        entries.retain(|e| ids.contains(&e.id));
+    }
+
+    if is_json {
+        print_json(&entries);
+        return Ok(());
+    }
+    if entries.is_empty() {
+        println!("{}", ui(&format!("{} cached in {}", if ids.is_empty() { "Nothing" } else { "None of those IDs are" }, dir.display())));
+        return Ok(());
+    }
+
+    let size = |b: Option<u64>| b.map_or("-".to_string(), |b| HumanBytes(b).to_string());
+    for e in &entries {
+        let when = e.cached.map_or("-".to_string(), |t| unix_to_hrtime(t).format("%Y-%m-%d %H:%M").to_string());
+        println!("{}", ui(&format!("#{} ▶  {} (cached {}; metadata {}, zip {})", e.id, e.name.as_deref().unwrap_or("(no metadata)"), when, size(e.metadata_bytes), size(e.zip_bytes))));
+    }
+    let total: u64 = entries.iter().map(|e| e.metadata_bytes.unwrap_or(0) + e.zip_bytes.unwrap_or(0)).sum();
+    println!("{}", ui(&format!("{} {}, {} in {}", entries.len(), if entries.len() == 1 { "entry" } else { "entries" }, HumanBytes(total), dir.display())));
+    Ok(())
+}
+
+/// Prints one cached submission in detail (where its metadata + zip are, their sizes, when stored). Specific to this project (s2w).
+pub fn s2w_cache_info(id: u16, is_json: bool) -> anyhow::Result<()> {
+    let dir = cache_dir().context("no cache directory (neither XDG_CACHE_HOME nor HOME/LOCALAPPDATA is set)")?;
+    let entry = if dir.exists() { cache_entries(&dir)? } else { vec![] }
+        .into_iter()
+        .find(|e| e.id == id)
+        .with_context(|| format!("#{} is not cached in {}", id, dir.display()))?;
+
+    if is_json {
+        print_json(&entry);
+    } else {
+        println!("{}", ui(&cache_info(&dir, &entry)));
+    }
+    Ok(())
+}
+
+/// The human-readable cache-info listing of an entry in dir.
+fn cache_info(dir: &Path, e: &CacheEntry) -> String {
+    let stored = |ext: &str, bytes: Option<u64>| bytes.map_or("-".to_string(), |b| format!("{} ({})", dir.join(format!("{}.{}", e.id, ext)).display(), HumanBytes(b)));
+    [
+        format!("#{} ▶  {}", e.id, e.name.as_deref().unwrap_or("(no metadata)")),
+        format!("  cached    {}", e.cached.map_or("-".to_string(), |t| unix_to_hrtime(t).format("%Y-%m-%d %H:%M").to_string())),
+        format!("  metadata  {}", stored("json", e.metadata_bytes)),
+        format!("  zip       {}", stored("zip", e.zip_bytes)),
+    ].join("\n")
+}
+
+/// Deletes everything in the cache directory. Specific to this project (s2w).
+pub fn s2w_clear_cache() -> anyhow::Result<()> {
+    let dir = cache_dir().context("no cache directory (neither XDG_CACHE_HOME nor HOME/LOCALAPPDATA is set)")?;
+    if !dir.exists() {
+        println!("{}", ui(&format!("Cache {} is already empty ✔", dir.display())));
+        return Ok(());
+    }
+
+    let mut freed = 0;
+    for entry in fs::read_dir(&dir).with_context(|| format!("could not read {}", dir.display()))? {
+        let path = entry?.path();
+        freed += fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        fs::remove_file(&path).with_context(|| format!("could not remove {}", path.display()))?;
+    }
+
+    println!("{}", ui(&format!("Cleared {} from {} ✔", HumanBytes(freed), dir.display())));
+    Ok(())
+}
+
+/// GETs a URL, retrying connection errors, timeouts and 5xx responses with exponential backoff. Specific to this project (s2w).
+fn s2w_get(client: &reqwest::blocking::Client, url: &str, retries: u32, log: &RunLog) -> reqwest::Result<reqwest::blocking::Response> {
+    let mut attempt = 0;
+    loop {
+        let res = client.get(url).send();
+        let why = match &res {
+            Ok(r) if r.status().is_server_error() || r.status() == StatusCode::TOO_MANY_REQUESTS => format!("HTTP {}", r.status()),
+            Err(e) if e.is_timeout() => "timed out".to_string(),
+            Err(e) if e.is_connect() => "could not connect".to_string(),
+            _ => return res,
+        };
+        if attempt >= retries {
+            return res;
+        }
+
+        attempt += 1;
+        // Rate limited/unavailable responses may say how long to back off; honor that (within reason) over our own schedule
+        let retry_after = res.as_ref().ok()
+            .and_then(|r| r.headers().get(RETRY_AFTER))
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(|secs| Duration::from_secs(secs.min(MAX_RETRY_AFTER)));
+        let wait = retry_after.unwrap_or(backoff_delay(attempt));
+        eprintln!("{}", ui(&format!("⚠ Request failed ({}), retrying in {:.1}s ({}/{})", why, wait.as_secs_f64(), attempt, retries)));
+        log.log(&format!("Retry {}/{} of {}: {}", attempt, retries, url, why));
+        sleep(wait);
+    }
+}
+
+/// Wait before retry number `attempt` (from 1): 0.5s, doubling each time, capped at MAX_BACKOFF.
+fn backoff_delay(attempt: u32) -> Duration {
+    2u64.checked_pow(attempt.saturating_sub(1))
+        .map(|f| Duration::from_millis(500u64.saturating_mul(f)))
+        .map_or(MAX_BACKOFF, |d| d.min(MAX_BACKOFF))
+}
+
+/// Downloads file at specified URL and updates provided indicatif bar, returning the bytes written. Specific to this project (s2w).
+fn s2w_download(url: &str, dest: &str, client: &reqwest::blocking::Client, retries: u32, log: &RunLog, no_progress: bool, no_delay: bool) -> anyhow::Result<u64> {
+    log.log(&format!("GET {} → {}", url, dest));
+    let mut resp = s2w_get(client, url, retries, log)
+        .and_then(|r| r.error_for_status())
+        .with_context(|| format!("failed to download {}", url))?;
+    log.log(&format!("Response {}", resp.status()));
+    let mut file = fs::File::create(dest).with_context(|| format!("could not create {}", dest))?;
+
+    // Chunked responses have no length to show a percentage of, so just count bytes
+    let total = resp.content_length();
+    let bar = s2w_bar(total.unwrap_or(0), no_progress);
+    match total {
+        Some(_) => bar.set_style(ProgressStyle::with_template("{bar:83} {percent:0}% ({bytes}/{total_bytes})")
+            .unwrap()
+            .progress_chars(bar_chars())),
+        None => {
+            bar.unset_length();
+            bar.set_style(ProgressStyle::with_template("{spinner} {bytes} ({bytes_per_sec})").unwrap().tick_chars(tick_chars()));
+        }
+    }
+
+    let mut dl_bytes = 0;
+    let mut chunk = [0u8; 1024];
+    loop {
+        let n = match io::Read::read(&mut resp, &mut chunk) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e).with_context(|| format!("download of {} was interrupted", url)),
+        };
+        file.write_all(&chunk[..n]).with_context(|| format!("could not write {}", dest))?;
+        dl_bytes += n as u64;
+        bar.set_position(dl_bytes);
+        if !no_delay {
+            sleep(Duration::from_millis(4)); // TODO: Most of these files are <50kb, so add a *very* tiny delay for user gratification! This theoretically shouldn't cause any problematic (artificial) waiting, but if making this function generic, stay wary of arbitrary dl size.
+        }
+    }
+
+    log.log(&format!("Downloaded {} bytes", dl_bytes));
+    bar.finish_and_clear();
+
+    if total.is_none() && !no_progress {
+        ow_print(&format!("Downloaded {}", HumanBytes(dl_bytes)));
+    }
+
+    Ok(dl_bytes)
+}
+
+/// Archive entries left out of extraction, with their magic-number type.
+type SkippedEntries = Vec<(PathBuf, Option<FileType>)>;
+
+/// Where each extracted file came from within the archive (extracted path → archive path).
+type EntrySources = HashMap<PathBuf, PathBuf>;
+
+/// Extracts files at provided location into dest and updates indicatif bar, returning the extracted paths. Specific to this project (s2w; only keeps .spc + any extra extensions).
+/// Skipped files are returned too (archive path + magic-number type), so an SPC-less pack can be explained, as is each file's archive path (flattening loses its folder).
+fn s2w_extract(loc: &str, dest: &Path, extra_exts: &[String], keep_dirs: bool, log: &RunLog, no_progress: bool, no_delay: bool) -> anyhow::Result<(Vec<PathBuf>, SkippedEntries, EntrySources)> {
+    // Modified from "zip" crate example https://github.com/zip-rs/zip2/blob/7c20fa30016301909bf2ade203cb4841b7776154/examples/extract.rs
+
+    let archive_file = fs::File::open(loc).with_context(|| format!("could not open {}", loc))?;
+    let mut archive = zip::ZipArchive::new(archive_file).with_context(|| format!("{} is not a valid zip archive", loc))?;
+
+    let bar = s2w_bar(archive.len() as u64, no_progress);
+    bar.set_style(ProgressStyle::with_template("{bar:83} {percent:0}% ({pos}/{len})")
+        .unwrap()
+        .progress_chars(bar_chars()));
+    bar.tick();
+
+    let mut extracted = vec![];
+    let mut skipped = vec![];
+    let mut sources: HashMap<PathBuf, PathBuf> = HashMap::new();
+
+    for i in 0..archive.len() {
+        bar.inc(1);
+
+        let mut file = archive.by_index(i).with_context(|| format!("could not read entry {} of {}", i, loc))?;
+
+        // Validate path (skip pass if invalid)
+        let fpath = match file.enclosed_name() {
+            Some(path) => path,
+            None => continue
+        };
+
+        let is_resource_fork = is_resource_fork(&fpath);
+
+        // Check file extension (skip pass if not .spc/kept — this implicitly skips directory entries)
+        let is_kept = fpath.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("spc") || extra_exts.iter().any(|x| x.eq_ignore_ascii_case(e)));
+
+        if !is_resource_fork && is_kept {
+            // Keep the archive's folders, or yank file to base directory (naming it after its folder too on a clash)
+            let bpath = match sources.get(&fpath) {
+                Some(seen) => seen.clone(),
+                None if keep_dirs => dest.join(&fpath),
+                None => flat_name(dest, &fpath, &extracted),
+            };
+            if let Some(parent) = bpath.parent() {
+                fs::create_dir_all(parent).with_context(|| format!("could not create {}", parent.display()))?;
+            }
+            let mut outfile = fs::File::create(&bpath).with_context(|| format!("could not create {}", bpath.display()))?;
+            io::copy(&mut file, &mut outfile).with_context(|| format!("could not extract {}", fpath.display()))?;
+            log.log(&format!("Extracted {} → {}", fpath.display(), bpath.display()));
+
+            if !extracted.contains(&bpath) {
+                sources.insert(fpath.clone(), bpath.clone());
+                extracted.push(bpath);
+            }
+        } else {
+            if !is_resource_fork && file.is_file() {
+                let mut head = vec![];
+                let _ = io::Read::read_to_end(&mut io::Read::take(&mut file, 64), &mut head);
+                skipped.push((fpath.clone(), magictype(&head)));
+            }
+
+            log.log(&format!("Skipped {}", fpath.display()));
+            if !no_delay {
+                sleep(Duration::from_millis(20));
+            }
+        }
+    }
+
+    // Delete zip file
+    fs::remove_file(loc).with_context(|| format!("could not delete {}", loc))?;
+
+    bar.finish_and_clear();
+    Ok((extracted, skipped, sources.into_iter().map(|(fpath, bpath)| (bpath, fpath)).collect()))
+}
+
+/// Sorts a pack's SPCs naturally ("2" before "10") by where they sat in the archive, and numbers the discs when they were
+/// split across subfolders ("Disc 1/", "Disc 2/"; a folder shared by every track doesn't count). None for a single folder.
+fn s2w_order_discs(spc_files: &mut [PathBuf], sources: &EntrySources) -> Option<Vec<u8>> {
+    let origin = |p: &PathBuf| sources.get(p).cloned().unwrap_or_else(|| p.clone());
+    spc_files.sort_by_cached_key(|p| (natural_key(&origin(p).to_string_lossy()), p.clone()));
+
+    let folders: Vec<PathBuf> = spc_files.iter().map(|p| origin(p).parent().map(Path::to_path_buf).unwrap_or_default()).collect();
+    let mut discs: Vec<&PathBuf> = folders.iter().collect();
+    discs.dedup();
+    if discs.len() < 2 {
+        return None;
+    }
+
+    // Sorted by path, so the same folder is always one run and runs come in natural order
+    Some(folders.iter().map(|f| (discs.iter().position(|d| *d == f).unwrap() + 1).min(u8::MAX as usize) as u8).collect())
+}
+
+/// Sort key comparing runs of digits by value, so "Track 2" comes before "Track 10" (case-insensitively otherwise).
+fn natural_key(s: &str) -> Vec<(String, Option<u128>)> {
+    regex!(r"(\D*)(\d*)").captures_iter(s)
+        .filter(|c| !c[0].is_empty())
+        .map(|c| (c[1].to_lowercase(), c[2].parse().ok().or((!c[2].is_empty()).then_some(u128::MAX))))
+        .collect()
+}
+
+/// Where a nested zip entry lands when flattened into dest: its own name, unless an already extracted file has the same stem
+/// (which would also collide once converted), then "name (folder).ext", then numbered.
+fn flat_name(dest: &Path, fpath: &Path, extracted: &[PathBuf]) -> PathBuf {
+    let is_taken = |p: &Path| extracted.iter().any(|e| e.file_stem().is_some_and(|s| Some(s.to_ascii_lowercase()) == p.file_stem().map(|s| s.to_ascii_lowercase())));
+
+    let name = dest.join(fpath.file_name().unwrap());
+    if !is_taken(&name) {
+        return name;
+    }
+
+    let stem = fpath.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = fpath.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    let folder = fpath.parent().and_then(|p| p.file_name()).map(|f| f.to_string_lossy());
+    let mut candidates = folder.iter().map(|f| format!("{} ({}){}", stem, f, ext)).collect::<Vec<_>>();
+    candidates.extend((2..).take(1000).map(|n| format!("{} ({}){}", stem, n, ext)));
+
+    candidates.into_iter()
+        .map(|c| dest.join(c))
+        .find(|c| !is_taken(c))
+        .unwrap_or(name)
+}
+
+/// Moves a file, falling back to copy + remove when rename can't cross filesystems (e.g. --output on another drive).
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => {
+            fs::copy(from, to)?;
+            fs::remove_file(from)
+        }
+        res => res,
+    }
+}
+
+/// Whether a zip entry is a macOS resource fork (__MACOSX/ or AppleDouble ._* files), which would otherwise pass as "._track.spc".
+fn is_resource_fork(fpath: &Path) -> bool {
+    fpath.components().any(|c| c.as_os_str() == "__MACOSX")
+        || fpath.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with("._"))
+}
+
+/// Lists the .spc entries (name + uncompressed size) of a downloaded zip without extracting anything. Specific to this project (s2w).
+fn s2w_list(loc: &str) -> anyhow::Result<Vec<(PathBuf, u64)>> {
+    let archive_file = fs::File::open(loc).with_context(|| format!("could not open {}", loc))?;
+    let mut archive = zip::ZipArchive::new(archive_file).with_context(|| format!("{} is not a valid zip archive", loc))?;
+
+    let mut tracks = vec![];
+    for i in 0..archive.len() {
+        let file = archive.by_index(i).with_context(|| format!("could not read entry {} of {}", i, loc))?;
+        let Some(fpath) = file.enclosed_name() else { continue };
+
+        let is_spc = fpath.extension().is_some_and(|e| e.eq_ignore_ascii_case("spc"));
+        if is_spc && file.is_file() && !is_resource_fork(&fpath) {
+            tracks.push((fpath, file.size()));
+        }
+    }
+
+    tracks.sort();
+    Ok(tracks)
+}
+
+/// Explains why a pack had no .spc to render, judging by what was in it instead.
+fn no_spc_reason(skipped: &[(PathBuf, Option<FileType>)]) -> String {
+    let count_ext = |exts: &[&str]| skipped.iter()
+        .filter(|(p, _)| p.extension().and_then(|e| e.to_str()).is_some_and(|e| exts.iter().any(|x| x.eq_ignore_ascii_case(e))))
+        .count();
+
+    let misnamed = skipped.iter().find(|(_, t)| matches!(t, Some(FileType::SPC)));
+    let samples = count_ext(&["brr", "bnk", "sf2", "sfz"]);
+    let roms = count_ext(&["smc", "sfc", "ips", "bps"]);
+
+    if let Some((p, _)) = misnamed {
+        format!("No .spc files found, but {} has an SPC header (misnamed?)", p.display())
+    } else if samples > 0 {
+        format!("This submission appears to be a sample pack ({} sample file(s)), not a playable SPC", samples)
+    } else if roms > 0 {
+        format!("This submission appears to be a ROM/patch ({} file(s)), not a playable SPC", roms)
+    } else if skipped.is_empty() {
+        "The downloaded archive is empty!".to_string()
+    } else {
+        let mut exts: Vec<String> = skipped.iter()
+            .map(|(p, _)| p.extension().map_or("(none)".to_string(), |e| format!(".{}", e.to_string_lossy().to_lowercase())))
+            .collect();
+        exts.sort();
+        exts.dedup();
+        format!("No .spc files found in the pack (only {})", exts.join(", "))
+    }
+}
+
+/// Converts specified .spc files to .wav using spc2wav utility (up to `jobs` at once) and updates indicatif bar (one step per track). Specific to this project (s2w).
+fn s2w_conv(locs: &[PathBuf], outdir_hint: Option<&Path>, log: &RunLog, no_progress: bool, no_delay: bool, jobs: usize) -> anyhow::Result<Vec<PathBuf>> {
+    let bar = s2w_bar(locs.len() as u64, no_progress);
+    bar.set_style(ProgressStyle::with_template("{bar:83} {percent:0}% ({pos}/{len})")
+        .unwrap()
+        .progress_chars(bar_chars()));
+
+    bar.tick();
+
+    let is_parallel = jobs > 1 && locs.len() > 1;
+    let wavs = par_map(locs, jobs, |i, loc| {
+        // spc2wav's output is found by what's new in its directory, so concurrent renders each get a directory of their own
+        let loc = if is_parallel {
+            let dir = loc.parent().unwrap_or(Path::new(".")).join(format!("track{}", i + 1));
+            fs::create_dir_all(&dir).with_context(|| format!("could not create {}", dir.display()))?;
+            let isolated = dir.join(loc.file_name().unwrap());
+            fs::rename(loc, &isolated).with_context(|| format!("could not move {}", loc.display()))?;
+            isolated
+        } else {
+            loc.clone()
+        };
+
+        let spinner = (is_parallel && !no_progress).then(|| {
+            let spinner = PROGRESS.insert_before(&bar, ProgressBar::new_spinner()
+                .with_style(ProgressStyle::with_template("{spinner} {msg}").unwrap().tick_chars(tick_chars()))
+                .with_message(loc.file_name().unwrap().to_string_lossy().to_string()));
+            spinner.enable_steady_tick(Duration::from_millis(100));
+            spinner
+        });
+
+        let mut wav = s2w_spc2wav(&loc, outdir_hint, log);
+        if let (true, Ok(rendered)) = (is_parallel, &wav) {
+            if rendered.parent() == loc.parent() {
+                let home = locs[i].with_file_name(rendered.file_name().unwrap());
+                wav = fs::rename(rendered, &home).map(|_| home).with_context(|| format!("could not move {}", rendered.display()));
+                let _ = fs::remove_dir(loc.parent().unwrap());
+            }
+        }
+        if let Some(spinner) = spinner {
+            spinner.finish_and_clear();
+            PROGRESS.remove(&spinner);
+        }
+        bar.inc(1);
+        wav
+    });
+
+    if !no_delay {
+        sleep(Duration::from_millis(10));
+    }
+    bar.finish_and_clear();
+    wavs.into_iter().collect()
+}
+
+/// Maps f over items on up to `jobs` scoped threads (a plain loop when jobs ≤ 1), keeping results in input order.
+fn par_map<T: Sync, R: Send>(items: &[T], jobs: usize, f: impl Fn(usize, &T) -> R + Sync) -> Vec<R> {
+    if jobs <= 1 || items.len() <= 1 {
+        return items.iter().enumerate().map(|(i, t)| f(i, t)).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+    std::thread::scope(|s| {
+        for _ in 0..jobs.min(items.len()) {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(i) else { break };
+                let r = f(i, item);
+                results.lock().unwrap()[i] = Some(r);
+            });
+        }
+    });
+
+    results.into_inner().unwrap().into_iter().map(|r| r.expect("every item is mapped once")).collect()
+}
+
+/// Runs spc2wav on a single .spc (consuming it), returning wherever the .wav ended up. Specific to this project (s2w).
+fn s2w_spc2wav(loc: &Path, outdir_hint: Option<&Path>, log: &RunLog) -> anyhow::Result<PathBuf> {
+    // spc2wav builds disagree on where the WAV lands, so diff every plausible directory around the run
+    let spc_dir = match loc.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let mut search_dirs = vec![spc_dir, env::current_dir().unwrap_or_else(|_| PathBuf::from("."))];
+    search_dirs.extend(outdir_hint.map(Path::to_path_buf));
+    let before: Vec<HashMap<PathBuf, SystemTime>> = search_dirs.iter().map(|d| wav_snapshot(d)).collect();
+
+    let mut cmd = Command::new(tool("spc2wav"));
+    cmd.arg(loc).current_dir(&search_dirs[0]);
+
+    let out = cmd.output().context("spc2wav could not be run (is it installed?)")?;
+    log.log_output(&cmd, &out);
+
+    fs::remove_file(loc).with_context(|| format!("could not delete {}", loc.display()))?;
+
+    let expected = loc.with_extension("wav");
+    let expected_name = expected.file_name().unwrap().to_os_string();
+    let mut found: Vec<PathBuf> = search_dirs.iter().zip(&before)
+        .flat_map(|(d, snap)| wav_snapshot(d).into_iter()
+            .filter(|(p, t)| snap.get(p).is_none_or(|old| old != t))
+            .map(|(p, _)| p)
+            .collect::<Vec<_>>())
+        .collect();
+    // Keep search_dirs order (the .spc's own dir first) when the same file was seen twice
+    let mut seen = HashSet::new();
+    found.retain(|p| seen.insert(p.clone()));
+
+    // Prefer the name we'd have assumed, wherever it ended up; otherwise take whatever new WAV showed up
+    let wav = found.iter().find(|p| p.file_name() == Some(expected_name.as_os_str()))
+        .or(found.first())
+        .cloned()
+        .or_else(|| expected.exists().then_some(expected))
+        .with_context(|| format!("spc2wav produced no .wav file for {} (searched {:?}); try --spc2wav-outdir", loc.display(), search_dirs))?;
+
+    log.log(&format!("spc2wav output found at {}", wav.display()));
+    Ok(wav)
+}
+
+/// Lists .wav files in a directory along with their modification times.
+fn wav_snapshot(dir: &Path) -> HashMap<PathBuf, SystemTime> {
+    fs::read_dir(dir).into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("wav")))
+        .filter_map(|p| Some((fs::canonicalize(&p).ok()?, fs::metadata(&p).ok()?.modified().ok()?)))
+        .collect()
+}
+
+/// Converts specified audio file to another format (by destination extension) using SoX. Specific to this project (s2w).
+/// Optionally applies gain so the peak lands at peak_target dBFS (measured with a `sox stat` pass first).
+/// trim_start (seconds) cuts the intro first, so the peak is measured on what's kept.
+fn s2w_sox(src: &Path, dest: &Path, opts: AudioOpts, trim_start: Option<f64>, peak_target: Option<f64>, log: &RunLog) -> anyhow::Result<()> {
+    let gain = peak_target.and_then(|target| match sox_peak(src, trim_start, log) {
+        Some(peak) => Some(peak_gain(peak, target)),
+        None => {
+            eprintln!("Could not measure peak level; skipping --peak-normalize-to");
+            None
+        }
+    });
+
+    run_encoder(&mut sox_command(src, dest, opts, trim_start, gain), "SoX", dest, log)
+}
+
+/// The SoX conversion of src into dest, with gain (dB) applied after the trim and fade.
+fn sox_command(src: &Path, dest: &Path, opts: AudioOpts, trim_start: Option<f64>, gain: Option<f64>) -> Command {
+    let mut cmd = Command::new(tool("sox"));
+    let (no_dither, dither) = sox_dither(opts.dither, audio_bits(src), opts.bits);
+    cmd.args(no_dither).arg(src);
+
+    // Output format options go between input and output; SoX inserts the rate/remix effects itself
+    if let Some(r) = opts.rate {
+        cmd.arg("-r").arg(r.to_string());
+    }
+    if let Some(b) = opts.bits {
+        cmd.arg("-b").arg(b.to_string());
+    }
+    if let Some(c) = opts.channels {
+        cmd.arg("-c").arg(c.to_string());
+    }
+    cmd.arg(dest);
+
+    // trim <start> [<length>], then fade toward the new end: fade <in> <stop> <out>
+    if trim_start.is_some() || opts.duration.is_some() {
+        cmd.arg("trim").arg(format!("{:.3}", trim_start.unwrap_or(0.0)));
+        if let Some(d) = opts.duration {
+            cmd.arg(format!("{:.3}", d));
+        }
+    }
+    if let (Some(d), Some(f)) = (opts.duration, opts.fade) {
+        cmd.arg("fade").arg("0").arg(format!("{:.3}", d)).arg(format!("{:.3}", f));
+    }
+
+    if let Some(g) = gain {
+        cmd.arg("gain").arg(format!("{:.3}", g));
+    }
+
+    // Last in the chain, so it requantizes what the other effects produced
+    cmd.args(dither);
+    cmd
+}
+
+/// Whether converting to bits would drop bit depth from the source's (unknown source depth: assume not).
+fn is_bit_reduction(src_bits: Option<u16>, bits: Option<u8>) -> bool {
+    matches!((src_bits, bits), (Some(s), Some(b)) if s > b as u16)
+}
+
+/// SoX arguments for --dither: global options (before the input) and effects (after the output). Nothing unless bit depth is reduced,
+/// nor without --dither, where SoX's automatic dithering applies.
+fn sox_dither(dither: Option<Dither>, src_bits: Option<u16>, bits: Option<u8>) -> (&'static [&'static str], &'static [&'static str]) {
+    if !is_bit_reduction(src_bits, bits) {
+        return (&[], &[]);
+    }
+
+    match dither {
+        Some(Dither::None) => (&["-D"], &[]),
+        Some(Dither::Tpdf) => (&[], &["dither"]),
+        Some(Dither::Shibata) => (&[], &["dither", "-s"]),
+        None => (&[], &[]),
+    }
+}
+
+/// ffmpeg resampler filter for --dither, if bit depth is reduced (ffmpeg doesn't dither by default, so --dither none needs nothing).
+fn ffmpeg_dither(dither: Option<Dither>, src_bits: Option<u16>, bits: Option<u8>) -> Option<&'static str> {
+    if !is_bit_reduction(src_bits, bits) {
+        return None;
+    }
+
+    match dither? {
+        Dither::None => None,
+        Dither::Tpdf => Some("aresample=dither_method=triangular"),
+        Dither::Shibata => Some("aresample=dither_method=shibata"),
+    }
+}
+
+/// Bits per sample of a WAV, AIFF or FLAC file, from its header; None if it's none of those (or unreadable).
+fn audio_bits(loc: &Path) -> Option<u16> {
+    let mut head = vec![];
+    io::Read::read_to_end(&mut io::Read::take(fs::File::open(loc).ok()?, 64 * 1024), &mut head).ok()?;
+    let be16 = |i: usize| Some(u16::from_be_bytes(head.get(i..i + 2)?.try_into().ok()?));
+    let le16 = |i: usize| Some(u16::from_le_bytes(head.get(i..i + 2)?.try_into().ok()?));
+
+    // RIFF/FORM files are a run of [id, size] chunks after the 12-byte header; the format chunk has the depth
+    let chunk = |id: &[u8], is_le: bool| {
+        let mut pos = 12;
+        while let Some(hdr) = head.get(pos..pos + 8) {
+            let size = if is_le { u32::from_le_bytes(hdr[4..8].try_into().ok()?) } else { u32::from_be_bytes(hdr[4..8].try_into().ok()?) };
+            if &hdr[..4] == id {
+                return Some(pos + 8);
+            }
+            pos = pos.checked_add(8 + size as usize + (size as usize & 1))?;
+        }
+        None
+    };
+
+    if head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WAVE") {
+        le16(chunk(b"fmt ", true)? + 14)
+    } else if head.starts_with(b"FORM") && matches!(head.get(8..12), Some(b"AIFF" | b"AIFC")) {
+        be16(chunk(b"COMM", false)? + 6)
+    } else if head.starts_with(b"fLaC") {
+        // STREAMINFO (always the first block): bits per sample - 1 spans bytes 12-13 of it
+        let info = head.get(8..8 + 34)?;
+        Some((((info[12] & 1) as u16) << 4 | (info[13] >> 4) as u16) + 1)
+    } else {
+        None
+    }
+}
+
+/// Runs a SoX/ffmpeg conversion, failing (with its stderr, and without the partial output) on a non-zero exit.
+fn run_encoder(cmd: &mut Command, name: &str, dest: &Path, log: &RunLog) -> anyhow::Result<()> {
+    let out = cmd.output().with_context(|| format!("{} could not be run", name))?;
+    log.log_output(cmd, &out);
+
+    if !out.status.success() {
+        let _ = fs::remove_file(dest);
+        let stderr = String::from_utf8_lossy(&out.stderr);
+        bail!("{} failed converting to {} ({}): {}", name, dest.display(), out.status, stderr.trim());
+    }
+
+    Ok(())
+}
+
+/// Parses a length given as plain seconds ("95", "95.5") or minutes:seconds ("1:35").
+pub fn parse_duration(s: &str) -> Result<f64, String> {
+    let secs = match s.split_once(':') {
+        Some((m, sec)) => {
+            let m: u32 = m.trim().parse().map_err(|_| format!("bad minutes in \"{}\"", s))?;
+            let sec: f64 = sec.trim().parse().map_err(|_| format!("bad seconds in \"{}\"", s))?;
+            if !(0.0..60.0).contains(&sec) {
+                return Err(format!("seconds in \"{}\" must be below 60", s));
+            }
+            m as f64 * 60.0 + sec
+        }
+        None => s.trim().parse().map_err(|_| format!("\"{}\" is neither seconds nor mm:ss", s))?,
+    };
+
+    if secs.is_finite() && secs >= 0.0 {
+        Ok(secs)
+    } else {
+        Err(format!("\"{}\" is not a valid length", s))
+    }
+}
+
+/// Converts src to dest (format by extension) with the chosen encoder. Specific to this project (s2w).
+fn s2w_encode(encoder: Encoder, src: &Path, dest: &Path, opts: AudioOpts, trim_start: Option<f64>, peak_target: Option<f64>, log: &RunLog) -> anyhow::Result<()> {
+    match encoder {
+        Encoder::Sox => s2w_sox(src, dest, opts, trim_start, peak_target, log),
+        Encoder::Ffmpeg => s2w_ffmpeg(src, dest, opts, trim_start, peak_target, log),
+    }
+}
+
+/// Converts via ffmpeg, mirroring s2w_sox's trim + peak gain. Specific to this project (s2w).
+fn s2w_ffmpeg(src: &Path, dest: &Path, opts: AudioOpts, trim_start: Option<f64>, peak_target: Option<f64>, log: &RunLog) -> anyhow::Result<()> {
+    let mut cmd = Command::new(tool("ffmpeg"));
+    cmd.args(["-y", "-loglevel", "error"]);
+    if let Some(t) = trim_start {
+        cmd.arg("-ss").arg(format!("{:.3}", t));
+    }
+    cmd.arg("-i").arg(src);
+    if let Some(d) = opts.duration {
+        cmd.arg("-t").arg(format!("{:.3}", d));
+    }
+
+    let mut filters = vec![];
+    if let (Some(d), Some(f)) = (opts.duration, opts.fade) {
+        filters.push(format!("afade=t=out:st={:.3}:d={:.3}", d - f, f));
+    }
+    if let Some(target) = peak_target {
+        match ffmpeg_peak(src, trim_start, log) {
+            Some(peak) => filters.push(format!("volume={:.3}dB", peak_gain(peak, target))),
+            None => eprintln!("Could not measure peak level; skipping --peak-normalize-to"),
+        }
+    }
+    if let Some(dither) = ffmpeg_dither(opts.dither, audio_bits(src), opts.bits) {
+        filters.push(dither.to_string());
+    }
+    if !filters.is_empty() {
+        cmd.arg("-af").arg(filters.join(","));
+    }
+
+    if let Some(r) = opts.rate {
+        cmd.arg("-ar").arg(r.to_string());
+    }
+    if let Some(c) = opts.channels {
+        cmd.arg("-ac").arg(c.to_string());
+    }
+    // FLAC takes its depth from the sample format (24-bit rides in s32), AIFF from the PCM codec
+    let is_aiff = dest.extension().is_some_and(|e| e.eq_ignore_ascii_case("aiff"));
+    match (opts.bits, is_aiff) {
+        (Some(b), true) => { cmd.arg("-c:a").arg(format!("pcm_s{}be", b)); }
+        (Some(16), false) => { cmd.args(["-sample_fmt", "s16"]); }
+        (Some(b), false) => { cmd.args(["-sample_fmt", "s32", "-bits_per_raw_sample"]).arg(b.to_string()); }
+        (None, _) => {}
+    }
+
+    cmd.arg(dest);
+    run_encoder(&mut cmd, "ffmpeg", dest, log)
+}
+
+/// Measures the peak amplitude (0..1 linear) of an audio file via ffmpeg's volumedetect filter.
+fn ffmpeg_peak(src: &Path, trim_start: Option<f64>, log: &RunLog) -> Option<f64> {
+    let mut cmd = Command::new(tool("ffmpeg"));
+    cmd.arg("-hide_banner");
+    if let Some(t) = trim_start {
+        cmd.arg("-ss").arg(format!("{:.3}", t));
+    }
+    cmd.arg("-i").arg(src).args(["-af", "volumedetect", "-f", "null", "-"]);
+
+    let out = cmd.output().ok()?;
+    log.log_output(&cmd, &out);
+
+    // volumedetect reports on stderr, e.g. "[Parsed_volumedetect_0 @ 0x…] max_volume: -3.2 dB"
+    let max_db = String::from_utf8_lossy(&out.stderr).lines()
+        .find_map(|l| l.split("max_volume:").nth(1).map(|v| v.trim().trim_end_matches("dB").trim().to_string()))?
+        .parse::<f64>().ok()?;
+
+    Some(10f64.powf(max_db / 20.0))
+}
+
+/// Measures the peak amplitude (0..1 linear) of an audio file via `sox <file> -n stat`.
+fn sox_peak(src: &Path, trim_start: Option<f64>, log: &RunLog) -> Option<f64> {
+    let mut cmd = Command::new(tool("sox"));
+    cmd.arg(src).arg("-n");
+    if let Some(t) = trim_start {
+        cmd.arg("trim").arg(format!("{:.3}", t));
+    }
+    cmd.arg("stat");
+
+    let out = cmd.output().ok()?;
+    log.log_output(&cmd, &out);
+
+    // stat reports on stderr, e.g. "Maximum amplitude:     0.823456"
+    let report = String::from_utf8_lossy(&out.stderr);
+    let amplitude = |label: &str| report.lines()
+        .find(|l| l.starts_with(label))
+        .and_then(|l| l.split(':').nth(1))
+        .and_then(|v| v.trim().parse::<f64>().ok());
+
+    let peak = amplitude("Maximum amplitude")?.abs().max(amplitude("Minimum amplitude")?.abs());
+    Some(peak)
+}
+
+/// Computes the gain (dB) needed to move a linear peak amplitude to the target dBFS. Silence needs none.
+fn peak_gain(peak: f64, target: f64) -> f64 {
+    if peak <= 0.0 {
+        return 0.0;
+    }
+
+    target - 20.0 * peak.log10()
+}
+
+/// Best-guess song name from an SPC's filename, minus any leading track number ("03 - Forest.spc" → "Forest"), unless raw.
+/// A bare number ("03.spc") is all there is to go on, so it's written per the style. Specific to this project (s2w).
+fn spc_title(loc: &Path, style: TrackTitleStyle) -> String {
+    let stem = loc.file_stem().map(|s| s.to_string_lossy().replace('_', " ")).unwrap_or_default();
+    let stem = stem.trim();
+    let digits = stem.trim_start_matches(|c: char| c.is_ascii_digit());
+    let number = &stem[..stem.len() - digits.len()];
+    let name = digits.trim_start_matches([' ', '-', '.', ')']).trim();
+
+    match style {
+        TrackTitleStyle::Raw => stem.to_string(),
+        _ if !name.is_empty() || number.is_empty() => name.to_string(),
+        TrackTitleStyle::Stripped => strip_zeros(number).to_string(),
+        TrackTitleStyle::Ordinal => ordinal(number),
+    }
+}
+
+/// A run of digits without its leading zeros ("03" → "3", "00" → "0").
+fn strip_zeros(digits: &str) -> &str {
+    match digits.trim_start_matches('0') {
+        "" if !digits.is_empty() => "0",
+        n => n,
+    }
+}
+
+/// Reads each SPC's own tag. As an album, tracks without an xid6 number go by their filename's number, else
+/// filename order within their disc (and untitled ones by filename), so even a lone SPC becomes track 1. Specific to this project (s2w).
+fn s2w_track_tags(spc_files: &[PathBuf], discs: Option<&[u8]>, is_album: bool, style: TrackTitleStyle) -> Vec<Option<ID666>> {
+    let mut disc_lens: HashMap<Option<u8>, usize> = HashMap::new();
+    spc_files.iter().enumerate().map(|(i, spc)| {
+        let mut tag = fs::read(spc).ok()
+            .and_then(|d| parse_spc_header(&d).ok())
+            .and_then(|h| h.id666);
+        let disc = discs.and_then(|d| d.get(i).copied());
+        let pos = *disc_lens.entry(disc).and_modify(|n| *n += 1).or_insert(1);
+        if is_album {
+            let tag = tag.get_or_insert_with(ID666::default);
+            tag.track.get_or_insert_with(|| spc_track_number(spc).unwrap_or(pos.min(u8::MAX as usize) as u8));
+            if let Some(d) = disc {
+                tag.disc.get_or_insert(d);
+            }
+            if tag.song.is_empty() {
+                tag.song = spc_title(spc, style);
+            }
+        }
+        tag
+    }).collect()
+}
+
+/// Picks the one SPC of a pack to convert under --no-album-mode: asked for when interactive, else the first. Specific to this project (s2w).
+fn s2w_pick_track(spc_files: &[PathBuf], is_interactive: bool) -> anyhow::Result<PathBuf> {
+    let name = |p: &PathBuf| p.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    if !is_interactive {
+        eprintln!("{}", ui(&format!("⚠ Album mode is off; converting only {} of {} tracks", name(&spc_files[0]), spc_files.len())));
+        return Ok(spc_files[0].clone());
+    }
+
+    let pick = Select::new("Album mode is off; which track should be converted?", spc_files.iter().map(name).collect()).raw_prompt().context("No choice!")?;
+    Ok(spc_files[pick.index].clone())
+}
+
+/// Track number from an SPC filename's leading digits ("03 - Forest.spc" → 3), if it has any that fit.
+fn spc_track_number(loc: &Path) -> Option<u8> {
+    let stem = loc.file_stem()?.to_string_lossy();
+    let number: String = stem.trim().chars().take_while(|c| c.is_ascii_digit()).collect();
+    number.parse().ok().filter(|&n| n != 0)
+}
+
+/// English ordinal for a run of digits ("01" → "1st", "12" → "12th", "23" → "23rd"), however long.
+fn ordinal(digits: &str) -> String {
+    let n = strip_zeros(digits);
+    let last: u8 = n[n.len().saturating_sub(2)..].parse().unwrap_or(0);
+    let suffix = match (last % 10, last) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+/// Writes the submission's metadata (+ the SPC's own tag, if known) to the specified audio file, failing with whichever tags couldn't be written. Specific to this project (s2w).
+fn s2w_tag(loc: &Path, file: &SMWCFile, opts: &TagOpts, cover: Option<&(Vec<u8>, MimeType)>, spc_tag: Option<&ID666>) -> anyhow::Result<()> {
+    let artists = author_names(&file.authors, file.submitter.as_ref());
+
+    // Numbered tracks come from a pack, where the submission name is the album and each SPC names its own song
+    let title = spc_tag.filter(|t| t.track.is_some() && !t.song.is_empty())
+        .map_or_else(|| file.name.clone(), |t| t.song.clone());
+
+    let mut basic = vec![
+        ("title", title),
+        ("artist", artists.join(", ")),
+        ("date", opts.year.unwrap_or_else(|| unix_to_hrtime(file.time).year()).to_string()),
+        ("comment", "Processed by smwc2wav".to_string()),
+        ("genre", opts.genre.clone().unwrap_or_else(|| "Game".to_string())),
+    ];
+
+    basic.push(("album", opts.album.clone().unwrap_or_else(|| file.name.clone())));
+
+    // The SPC's own (x)ID666 tag knows per-track details the submission doesn't
+    let mut freeform = vec![];
+    if let Some(id666) = spc_tag {
+        if let Some(track) = id666.track {
+            basic.push(("track", track.to_string()));
+        }
+        if let Some(disc) = id666.disc.filter(|&d| d != 0) {
+            basic.push(("disc", disc.to_string()));
+        }
+        if !id666.artist.is_empty() {
+            basic.push(("composer", id666.artist.clone()));
+        }
+        if !id666.publisher.is_empty() {
+            freeform.push(("PUBLISHER", id666.publisher.clone()));
+        }
+        if let Some(l) = id666.loop_length {
+            freeform.push(("LOOP_LENGTH", format!("{:.3}", l)));
+        }
+    }
+
+    // Which game the tune comes from; no standard frame for it, so it rides along as a custom one
+    let source = file.raw_fields.source.trim();
+    if !source.is_empty() && !["n/a", "none", "-"].contains(&source.to_ascii_lowercase().as_str()) {
+        freeform.push(("SMWC_SOURCE", source.to_string()));
+    }
+
+    // SMWC popularity stats, as of this download
+    if opts.is_stats {
+        freeform.push(("SMWC_DOWNLOADS", file.downloads.to_string()));
+        if let Some(r) = file.rating {
+            freeform.push(("SMWC_RATING", format!("{:.2}", r)));
+        }
+        freeform.push(("SMWC_FEATURED", file.raw_fields.featured.to_string()));
+    }
+
+    // Keep going past a failed tagger so everything else still gets written, then say what's missing
+    let keys = |fields: &[(&str, String)]| fields.iter().map(|(k, _)| *k).collect::<Vec<&str>>().join("/");
+    let mut failed = vec![];
+    match TagContainer::of(loc) {
+        TagContainer::Audiotags => if let Err(e) = tag_basic(loc, &basic, cover) {
+            eprintln!("audiotags could not tag {} ({})", loc.display(), e);
+
+            match tag_ffmpeg(loc, &basic) {
+                Ok(()) => if cover.is_some() { eprintln!("Tagged via ffmpeg instead, without cover art") },
+                Err(e) => failed.push(format!("could not write {} tags ({})", keys(&basic), e)),
+            }
+        },
+        // audiotags can't open WAV/AIFF, but an ID3 chunk in the container reads fine almost everywhere
+        TagContainer::Id3Chunk => if let Err(e) = tag_wav(loc, &basic, cover) {
+            failed.push(format!("could not tag {} ({})", loc.display(), e));
+        },
+        // Ogg/Opus/AAC: ffmpeg writes any key, freeform ones included, but can't attach a picture here
+        TagContainer::Ffmpeg => {
+            if cover.is_some() {
+                eprintln!("{}", ui(&format!("⚠ Cover art can't be embedded in {}; tagging without it", loc.display())));
+            }
+            let all = [basic.as_slice(), freeform.as_slice()].concat();
+            if let Err(e) = tag_ffmpeg(loc, &all) {
+                failed.push(format!("could not write {} tags ({})", keys(&all), e));
+            }
+            freeform.clear();
+        }
+    }
+
+    if let Err(e) = tag_freeform(loc, &freeform) {
+        failed.push(format!("could not write {} tags ({})", keys(&freeform), e));
+    }
+
+    // Joined artist string above stays as the fallback if the format can't hold several values
+    if opts.is_multi_artist && artists.len() > 1 {
+        if let Err(e) = tag_artists(loc, &artists) {
+            failed.push(format!("could not write multiple artist values ({})", e));
+        }
+    }
+
+    if !failed.is_empty() {
+        bail!("{}", failed.join("; "));
+    }
+    Ok(())
+}
+
+/// Which tagger can write (cover art included) to an output file, by its extension.
+#[derive(Debug, PartialEq)]
+enum TagContainer {
+    Audiotags, // mp3/flac/m4a
+    Id3Chunk, // wav/aiff
+    Ffmpeg, // everything else, without cover art
+}
+
+impl TagContainer {
+    pub fn of(loc: &Path) -> TagContainer {
+        let ext = loc.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).unwrap_or_default();
+        match ext.as_str() {
+            "mp3" | "flac" | "m4a" | "mp4" => TagContainer::Audiotags,
+            "wav" | "aiff" | "aif" => TagContainer::Id3Chunk,
+            _ => TagContainer::Ffmpeg,
+        }
+    }
+}
+
+/// Writes the common tags (ffmpeg metadata key names) + cover via audiotags.
+fn tag_basic(loc: &Path, basic: &[(&str, String)], cover: Option<&(Vec<u8>, MimeType)>) -> Result<(), audiotags::Error> {
+    let mut tag = Tag::default().read_from_path(loc)?;
+
+    for (key, value) in basic {
+        match *key {
+            "title" => tag.set_title(value),
+            "artist" => tag.set_artist(value),
+            "album" => tag.set_album(Album::with_title(value)),
+            "date" => if let Ok(y) = value.parse() { tag.set_year(y) },
+            "comment" => tag.set_comment(value.clone()),
+            "genre" => tag.set_genre(value),
+            "track" => if let Ok(t) = value.parse() { tag.set_track_number(t) },
+            "disc" => if let Ok(d) = value.parse() { tag.set_disc_number(d) },
+            "composer" => tag.set_composer(value.clone()),
+            _ => {}
+        }
+    }
+
+    if let Some((ca_file, ca_mime)) = cover {
+        tag.set_album_cover(Picture::new(ca_file, *ca_mime));
+    }
+
+    let path = loc.to_str().ok_or_else(|| Error::new(ErrorKind::InvalidInput, "path is not valid UTF-8"))?;
+    tag.write_to_path(path)
+}
+
+/// Writes the common tags + cover as an ID3v2.4 chunk inside a WAV or AIFF (RIFF/FORM "id3 " chunk).
+fn tag_wav(loc: &Path, basic: &[(&str, String)], cover: Option<&(Vec<u8>, MimeType)>) -> id3::Result<()> {
+    use id3::TagLike;
+    let mut tag = id3::Tag::read_from_path(loc).unwrap_or_default();
+
+    for (key, value) in basic {
+        match *key {
+            "title" => tag.set_title(value),
+            "artist" => tag.set_artist(value),
+            "album" => tag.set_album(value),
+            "date" => if let Ok(y) = value.parse() { tag.set_year(y) },
+            "comment" => { tag.add_frame(id3::frame::Comment { lang: "eng".to_string(), description: String::new(), text: value.clone() }); }
+            "genre" => tag.set_genre(value),
+            "track" => if let Ok(t) = value.parse() { tag.set_track(t) },
+            "disc" => if let Ok(d) = value.parse() { tag.set_disc(d) },
+            "composer" => tag.set_text("TCOM", value),
+            _ => {}
+        }
+    }
+
+    if let Some((ca_file, ca_mime)) = cover {
+        tag.add_frame(id3::frame::Picture { mime_type: String::from(*ca_mime), picture_type: id3::frame::PictureType::CoverFront, description: String::new(), data: ca_file.clone() });
+    }
+
+    tag.write_to_path(loc, id3::Version::Id3v24)
+}
+
+/// Fallback tagger for files audiotags can't open: remuxes through ffmpeg with -metadata (no re-encode).
+fn tag_ffmpeg(loc: &Path, basic: &[(&str, String)]) -> Result<(), Error> {
+    if which(tool("ffmpeg")).is_err() {
+        return Err(Error::new(ErrorKind::NotFound, "ffmpeg not installed for fallback tagging"));
+    }
+
+    let name = loc.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let tmp = loc.with_file_name(format!(".tagging.{}", name));
+
+    let mut cmd = Command::new(tool("ffmpeg"));
+    cmd.args(["-y", "-loglevel", "error", "-i"]).arg(loc).args(["-map", "0", "-codec", "copy"]);
+    for (key, value) in basic {
+        cmd.arg("-metadata").arg(format!("{}={}", key, value));
+    }
+    cmd.arg(&tmp);
+
+    let out = cmd.output()?;
+    if !out.status.success() {
+        let _ = fs::remove_file(&tmp);
+        return Err(Error::other(String::from_utf8_lossy(&out.stderr).trim().to_string()));
+    }
+
+    fs::rename(&tmp, loc)
+}
+
+/// Writes each artist as its own value (ID3v2.4 multi-value TPE1, repeated ARTIST comments, MP4 multi-value ©ART).
+fn tag_artists(loc: &Path, artists: &[String]) -> Result<(), Error> {
+    let to_err = |e: &dyn std::fmt::Display| Error::other(e.to_string());
+    let ext = loc.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).unwrap_or_default();
+
+    match ext.as_str() {
+        "mp3" | "wav" | "aiff" | "aif" => {
+            use id3::TagLike;
+            let mut tag = id3::Tag::read_from_path(loc).map_err(|e| to_err(&e))?;
+            tag.set_text_values("TPE1", artists);
+            tag.write_to_path(loc, id3::Version::Id3v24).map_err(|e| to_err(&e))
+        }
+        "flac" => {
+            let mut tag = metaflac::Tag::read_from_path(loc).map_err(|e| to_err(&e))?;
+            tag.vorbis_comments_mut().set("ARTIST", artists.to_vec());
+            tag.save().map_err(|e| to_err(&e))
+        }
+        "m4a" | "mp4" => {
+            let mut tag = mp4ameta::Tag::read_from_path(loc).map_err(|e| to_err(&e))?;
+            tag.set_artists(artists.to_vec());
+            tag.write_to_path(loc).map_err(|e| to_err(&e))
+        }
+        _ => Err(Error::new(ErrorKind::Unsupported, format!("no multi-value artist support for .{}", ext))),
+    }
+}
+
+/// Opens the directory containing the specified file in the system file manager. Headless systems only get a warning.
+fn s2w_reveal(loc: &Path) {
+    reveal_with(loc, |dir| opener::open(dir))
+}
+
+/// s2w_reveal, handing the resolved (absolute) directory to `open`.
+fn reveal_with<E: std::fmt::Display>(loc: &Path, open: impl FnOnce(&Path) -> Result<(), E>) {
+    let dir = match loc.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let dir = fs::canonicalize(dir).unwrap_or(dir.to_path_buf());
+
+    if let Err(e) = open(&dir) {
+        eprintln!("{}", ui(&format!("\x1B[38;2;255;196;0m⚠ Could not open {} in a file manager ({})\x1B[0m", dir.display(), e)));
+    }
+}
+
+/// Writes freeform key/value tags that audiotags has no setters for (ID3 TXXX, Vorbis comments, MP4 freeform atoms).
+fn tag_freeform(loc: &Path, fields: &[(&str, String)]) -> Result<(), Error> {
+    if fields.is_empty() {
+        return Ok(());
+    }
+
+    let to_err = |e: &dyn std::fmt::Display| Error::other(e.to_string());
+    let ext = loc.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).unwrap_or_default();
+
+    match ext.as_str() {
+        "mp3" | "wav" | "aiff" | "aif" => {
+            use id3::TagLike;
+            let mut tag = id3::Tag::read_from_path(loc).map_err(|e| to_err(&e))?;
+            for (key, value) in fields {
+                match *key {
+                    "PUBLISHER" => tag.set_text("TPUB", value),
+                    _ => { tag.add_frame(id3::frame::ExtendedText { description: key.to_string(), value: value.clone() }); }
+                }
+            }
+            tag.write_to_path(loc, id3::Version::Id3v24).map_err(|e| to_err(&e))
+        }
+        "flac" => {
+            let mut tag = metaflac::Tag::read_from_path(loc).map_err(|e| to_err(&e))?;
+            for (key, value) in fields {
+                tag.vorbis_comments_mut().set(*key, vec![value.clone()]);
+            }
+            tag.save().map_err(|e| to_err(&e))
+        }
+        "m4a" | "mp4" => {
+            let mut tag = mp4ameta::Tag::read_from_path(loc).map_err(|e| to_err(&e))?;
+            for (key, value) in fields {
+                tag.set_data(mp4ameta::FreeformIdent::new("com.apple.iTunes", key), mp4ameta::Data::Utf8(value.clone()));
+            }
+            tag.write_to_path(loc).map_err(|e| to_err(&e))
+        }
+        _ => Err(Error::new(ErrorKind::Unsupported, format!("no freeform tag support for .{}", ext))),
+    }
+}
+
+/// Transliterates the file name of a path to plain ASCII, also replacing characters FAT32 & co. reject.
+fn ascii_filename(loc: &Path) -> PathBuf {
+    let name = loc.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let ascii: String = deunicode::deunicode(&name)
+        .chars()
+        .map(|c| if c.is_ascii_control() || "<>:\"/\\|?*".contains(c) { '_' } else { c })
+        .collect();
+
+    loc.with_file_name(ascii)
+}
+
+/// Shows text as the current phase, replacing the previous phase's text (the line stays until ow_done/ow_end). Use format macro for stringf.
+fn ow_print(str: &str) {
+    if IS_PLAIN_STATUS.load(Ordering::Relaxed) {
+        println!("{}", ui(str));
+        return;
+    }
+
+    let mut phase = PHASE.lock().unwrap();
+    phase.get_or_insert_with(|| PROGRESS.insert(0, ProgressBar::new_spinner().with_style(ProgressStyle::with_template("{msg}").unwrap())))
+        .set_message(ui(str));
+}
+
+/// Shows text as the current phase's outcome and keeps it on screen; the next ow_print starts a fresh line.
+fn ow_done(str: &str) {
+    ow_print(str);
+    ow_end();
+}
+
+/// Leaves the current phase line as it is (e.g. when a step fails), so later output goes below it.
+pub fn ow_end() {
+    if let Some(phase) = PHASE.lock().unwrap().take() {
+        let last = phase.message();
+        phase.finish_and_clear();
+        PROGRESS.suspend(|| println!("{}", last));
+    }
+}
+
+/// Passes display text through as-is, or under --ascii-ui swaps glyphs for ASCII and transliterates the rest.
+pub fn ui(str: &str) -> String {
+    if !IS_ASCII_UI.load(Ordering::Relaxed) {
+        return str.to_string();
+    }
+
+    ascii_ui(str)
+}
+
+/// Plain-ASCII rendering of UI text: glyphs swapped for their stand-ins, anything else non-ASCII transliterated.
+fn ascii_ui(str: &str) -> String {
+    let swapped: String = str.chars()
+        .map(|c| ASCII_GLYPHS.iter().find(|(g, _)| *g == c).map_or(c.to_string(), |(_, a)| a.to_string()))
+        .collect();
+    deunicode::deunicode(&swapped)
+}
+
+/// Whether a path is a file that can be run: executable bits on Unix, just a file elsewhere (Windows has no such bit).
+fn is_executable(loc: &Path) -> bool {
+    let Ok(meta) = fs::metadata(loc) else { return false };
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        meta.is_file() && meta.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        meta.is_file()
+    }
+}
+
+/// Resolves an external tool to its --*-path override, or the bare name (looked up on PATH).
+fn tool(name: &str) -> PathBuf {
+    TOOL_PATHS.get()
+        .and_then(|t| t.get(name))
+        .cloned()
+        .unwrap_or_else(|| PathBuf::from(name))
+}
+
+/// Validates and installs the --sox-path/--ffmpeg-path/--spc2wav-path overrides (once, before anything runs a tool).
+pub fn set_tool_paths(overrides: &[(&'static str, Option<&Path>)], log: &RunLog) -> anyhow::Result<()> {
+    let mut tool_paths = HashMap::new();
+    for (name, path) in overrides {
+        if let Some(path) = path {
+            if !is_executable(path) {
+                bail!("--{}-path {} is not an executable file!", name, path.display());
+            }
+            log.log(&format!("Using {} at {}", name, path.display()));
+            tool_paths.insert(*name, path.to_path_buf());
+        }
+    }
+
+    TOOL_PATHS.set(tool_paths).map_err(|_| anyhow::anyhow!("tool paths were already set"))
+}
+
+/// Whether a tool (its override, else whatever is on PATH) can be found.
+pub fn has_tool(name: &str) -> bool {
+    which(tool(name)).is_ok()
+}
+
+/// Draws the UI with plain ASCII only from here on (--ascii-ui).
+pub fn set_ascii_ui(is_ascii: bool) {
+    IS_ASCII_UI.store(is_ascii, Ordering::Relaxed);
+}
+
+/// Prints status lines plainly (one per update) rather than redrawing them in place.
+pub fn set_plain_status(is_plain: bool) {
+    IS_PLAIN_STATUS.store(is_plain, Ordering::Relaxed);
+}
+
+/// Fill characters for progress bars, per --ascii-ui.
+fn bar_chars() -> &'static str {
+    if IS_ASCII_UI.load(Ordering::Relaxed) { "#=-" } else { "█▒░" }
+}
+
+/// Tick characters for spinners, per --ascii-ui.
+fn tick_chars() -> &'static str {
+    if IS_ASCII_UI.load(Ordering::Relaxed) { "|/-\\ " } else { "⠁⠂⠄⡀⢀⠠⠐⠈ " }
+}
+
+/// Converts byte(s) to hex representation in string.
+fn hex_str(bytes: &[u8], delim: &str) -> String {
+    bytes.iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<String>>()
+        .join(delim)
+}
+
+/// Get filetype by magic number, looking only at the first MAGIC_HEAD_LEN bytes (+ the last two, for MBR's 55AA).
+/// Note standards may change, # not present, etc.
+fn magictype(data: &[u8]) -> Option<FileType> {
+    let head = &data[..data.len().min(MAGIC_HEAD_LEN)];
+    let tail = &data[data.len().saturating_sub(2)..];
+
+    MAGIC_REGEXES.iter().find(|(f, re)| magic_window(head, tail, f, re)).map(|(f, _)| *f)
+}
+
+/// Like magictype, but only reads the bytes it checks instead of the whole file.
+pub fn magictype_from_path(loc: &Path) -> io::Result<Option<FileType>> {
+    let mut file = fs::File::open(loc)?;
+    let mut head = vec![];
+    io::Read::read_to_end(&mut io::Read::take(io::BufReader::new(&mut file), MAGIC_HEAD_LEN as u64), &mut head)?;
+
+    let mut tail = vec![];
+    if file.metadata()?.len() > 2 {
+        io::Seek::seek(&mut file, io::SeekFrom::End(-2))?;
+        io::Read::read_to_end(&mut file, &mut tail)?;
+    }
+
+    Ok(MAGIC_REGEXES.iter().find(|(f, re)| magic_window(&head, &tail, f, re)).map(|(f, _)| *f))
+}
+
+/// Matches a filetype's compiled magic against the header, or the trailing bytes for end-of-data signatures (MBR).
+fn magic_window(head: &[u8], tail: &[u8], ftype: &FileType, re: &Regex) -> bool {
+    let window = if *ftype == FileType::MBR { tail } else { head };
+    re.is_match(&hex_str(window, ";").to_uppercase())
+}
+
+/// Reads a local image (-c) and readies it as cover art. Specific to this project (s2w).
+pub fn load_cover(loc: &str) -> anyhow::Result<(Vec<u8>, MimeType)> {
+    let data = fs::read(loc).with_context(|| format!("Cover art image {} could not be read", loc))?;
+    let ftype = magictype(&data).context("Cover art file could not be identified")?;
+
+    s2w_cover(data, &ftype)
+}
+
+/// Readies cover art for tagging, transcoding WEBP to PNG since tag pictures can't carry it.
+/// Specific to this project (s2w).
+fn s2w_cover(data: Vec<u8>, ftype: &FileType) -> anyhow::Result<(Vec<u8>, MimeType)> {
+    match ftype {
+        FileType::WEBP => {
+            let img = image::load_from_memory_with_format(&data, image::ImageFormat::WebP).context("WEBP cover art could not be decoded")?;
+            let mut png = io::Cursor::new(vec![]);
+            img.write_to(&mut png, image::ImageFormat::Png).context("WEBP cover art could not be converted to PNG")?;
+            Ok((png.into_inner(), MimeType::Png))
+        }
+        FileType::HEIC => bail!("HEIC cover art isn't supported; convert it to JPEG or PNG first"),
+        _ => {
+            let mime = ftype.mime().with_context(|| format!("{} isn't a supported cover image (use JPEG or PNG)", ftype.as_ref()))?;
+            Ok((data, mime))
+        }
+    }
+}
+
+/// Inflates a body that still carries the gzip magic (1F 8B), e.g. a double-compressed response; anything else passes through.
+fn gunzip_lenient(body: &[u8]) -> Vec<u8> {
+    if !body.starts_with(&[0x1F, 0x8B]) {
+        return body.to_vec();
+    }
+
+    let mut inflated = vec![];
+    match io::Read::read_to_end(&mut flate2::read::MultiGzDecoder::new(body), &mut inflated) {
+        Ok(_) => inflated,
+        Err(_) => body.to_vec(),
+    }
+}
+
+/// Reads pixel dimensions from a PNG/GIF/BMP/JPEG header, None if unrecognized/truncated.
+fn image_dims(data: &[u8]) -> Option<(u32, u32)> {
+    let be32 = |i: usize| Some(u32::from_be_bytes(data.get(i..i + 4)?.try_into().ok()?));
+    let le16 = |i: usize| Some(u16::from_le_bytes(data.get(i..i + 2)?.try_into().ok()?) as u32);
+    let be16 = |i: usize| Some(u16::from_be_bytes(data.get(i..i + 2)?.try_into().ok()?) as u32);
+
+    match magictype(data)? {
+        FileType::PNG => Some((be32(16)?, be32(20)?)), // IHDR
+        FileType::GIF => Some((le16(6)?, le16(8)?)),
+        FileType::BMP => {
+            let w = i32::from_le_bytes(data.get(18..22)?.try_into().ok()?);
+            let h = i32::from_le_bytes(data.get(22..26)?.try_into().ok()?);
+            Some((w.unsigned_abs(), h.unsigned_abs()))
+        }
+        FileType::JPEG => {
+            // Walk marker segments until a start-of-frame (SOF0..SOF15, minus DHT/JPG/DAC)
+            let mut i = 2;
+            while *data.get(i)? == 0xFF {
+                let marker = *data.get(i + 1)?;
+                if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+                    return Some((be16(i + 7)?, be16(i + 5)?));
+                }
+                i += 2 + be16(i + 2)? as usize;
+            }
+            None
+        }
+        _ => None
+    }
+}
+
+/// Scores an image for use as cover art: area, weighted by how close to square it is.
+fn cover_score((w, h): (u32, u32)) -> f64 {
+    if w == 0 || h == 0 {
+        return 0.0;
+    }
+
+    (w as f64 * h as f64) * (w.min(h) as f64 / w.max(h) as f64)
+}
+
+/// Picks the best-scoring image out of a submission's image URLs, fetching each once. Specific to this project (s2w).
+/// Falls back to the first image if no dimensions could be read.
+fn s2w_auto_cover(urls: &[String], client: &reqwest::blocking::Client, retries: u32, log: &RunLog) -> Option<(Vec<u8>, MimeType)> {
+    let mut cache: HashMap<&str, Option<Vec<u8>>> = HashMap::new();
+
+    for url in urls {
+        if cache.contains_key(url.as_str()) {
+            continue;
+        }
+
+        let full = if url.starts_with("//") { format!("https:{}", url) } else { url.clone() };
+        log.log(&format!("GET {}", full));
+        let data = s2w_get(client, &full, retries, log).ok()
+            .filter(|r| r.status().is_success())
+            .and_then(|r| r.bytes().ok())
+            .map(|b| b.to_vec());
+        cache.insert(url, data);
+    }
+
+    let usable = |url: &String| {
+        let data = cache.get(url.as_str())?.as_ref()?;
+        magictype(data)?.mime().ok()?;
+        Some(data)
+    };
+
+    let best = urls.iter()
+        .filter_map(|u| Some((u, cover_score(image_dims(usable(u)?)?))))
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(u, _)| u)
+        .or(urls.first())?;
+
+    log.log(&format!("Auto cover: {}", best));
+    let data = usable(best)?;
+    s2w_cover(data.clone(), &magictype(data)?).ok()
+}
+
+/// Downloads one of a submission's images for use as cover art, identified (and transcoded if need be) like a local one. Specific to this project (s2w).
+fn s2w_image(url: &str, client: &reqwest::blocking::Client, retries: u32, log: &RunLog) -> anyhow::Result<(Vec<u8>, MimeType)> {
+    let full = if url.starts_with("//") { format!("https:{}", url) } else { url.to_string() };
+    log.log(&format!("GET {}", full));
+    let resp = s2w_get(client, &full, retries, log).with_context(|| format!("could not download {}", full))?;
+    if !resp.status().is_success() {
+        bail!("could not download {} (HTTP {})", full, resp.status());
+    }
+
+    let data = resp.bytes().with_context(|| format!("could not read {}", full))?.to_vec();
+    let ftype = magictype(&data).with_context(|| format!("{} could not be identified as an image", full))?;
+    s2w_cover(data, &ftype)
+}
+
+/// Reads a fixed-length, NUL-padded ID666 string field.
+fn id666_str(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).trim().to_string()
+}
+
+/// Reads a fixed-length ID666 numeric text field (e.g. "120"), None if empty/malformed.
+fn id666_num(field: &[u8]) -> Option<u32> {
+    id666_str(field).parse().ok()
+}
+
+/// Reads a little-endian ID666 binary numeric field, None if zero (i.e. unset).
+fn id666_bin(field: &[u8]) -> Option<u32> {
+    let res = field.iter().rev().fold(0u32, |acc, &b| (acc << 8) | b as u32);
+    (res != 0).then_some(res)
+}
+
+/// Parses the SPC file header and its ID666 tag.
+/// ID666 comes in a text and a binary flavour with shifted offsets and no flag distinguishing them, so guess by whether the length/fade fields look like digits.
+fn parse_spc_header(data: &[u8]) -> Result<SPCHeader, Error> {
+    if data.len() < 0x100 || !data.starts_with(b"SNES-SPC700 Sound File Data") {
+        return Err(Error::new(ErrorKind::InvalidData, "Not an SPC file (bad header)"));
+    }
+
+    let version = format!("0.{}", data[0x24]);
+
+    // Extended ID666 lives after the RAM/DSP dump and applies even without a fixed block
+    let xid6 = data.get(0x10200..).filter(|x| x.starts_with(b"xid6"));
+
+    // 0x23: 26 = has ID666, 27 = no ID666
+    if data[0x23] != 26 {
+        let id666 = xid6.map(|x| {
+            let mut id666 = ID666::default();
+            apply_xid6(&mut id666, x);
+            id666
+        });
+
+        return Ok(SPCHeader { version, id666_format: None, has_xid6: xid6.is_some(), id666 });
+    }
+
+    let is_text = data[0xA9..0xB1].iter().all(|&b| b == 0 || b.is_ascii_digit())
+        && data[0x9E..0xA9].iter().all(|&b| b == 0 || b.is_ascii_digit() || b == b'/' || b == b'-');
+
+    let mut id666 = ID666 {
+        song: id666_str(&data[0x2E..0x4E]),
+        game: id666_str(&data[0x4E..0x6E]),
+        dumper: id666_str(&data[0x6E..0x7E]),
+        comments: id666_str(&data[0x7E..0x9E]),
+        ..Default::default()
+    };
+
+    let emulator_id = if is_text {
+        id666.dumped = id666_str(&data[0x9E..0xA9]);
+        id666.length = id666_num(&data[0xA9..0xAC]);
+        id666.fade = id666_num(&data[0xAC..0xB1]);
+        id666.artist = id666_str(&data[0xB1..0xD1]);
+        data[0xD2].wrapping_sub(b'0')
+    } else {
+        // Binary date is day, month, then a u16 year
+        let year = u16::from_le_bytes([data[0xA0], data[0xA1]]);
+        if year != 0 {
+            id666.dumped = format!("{:02}/{:02}/{}", data[0x9F], data[0x9E], year);
+        }
+        id666.length = id666_bin(&data[0xA9..0xAC]);
+        id666.fade = id666_bin(&data[0xAC..0xB0]);
+        id666.artist = id666_str(&data[0xB0..0xD0]);
+        data[0xD1]
+    };
+
+    id666.emulator = match emulator_id {
+        1 => "ZSNES",
+        2 => "Snes9x",
+        3 => "ZST2SPC",
+        4 => "Other",
+        5 => "SNEShout",
+        6 => "ZSNES/W",
+        7 => "Snes9xpp",
+        8 => "SNESGT",
+        _ => "Unknown",
+    }.to_string();
+
+    if let Some(x) = xid6 {
+        apply_xid6(&mut id666, x);
+    }
+
+    Ok(SPCHeader {
+        version,
+        id666_format: Some(if is_text { "text" } else { "binary" }.to_string()),
+        has_xid6: xid6.is_some(),
+        id666: Some(id666),
+    })
+}
+
+/// Overlays the sub-chunks of an extended ID666 (xid6) chunk onto the tag, preferring them over the fixed block.
+/// Each sub-chunk is [id: u8, type: u8, len: u16] where type 0 stores its value in len itself; otherwise len bytes of data follow (padded to 4).
+fn apply_xid6(id666: &mut ID666, xid6: &[u8]) {
+    const TICKS_PER_SEC: f64 = 64000.0;
+
+    // A truncated header has no size to trust; leave the base ID666 as-is
+    let Some(size) = xid6.get(4..8) else {
+        return;
+    };
+    let size = u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as usize;
+    let chunks = &xid6[8..xid6.len().min(8usize.saturating_add(size))];
+
+    let mut pos = 0;
+    while pos + 4 <= chunks.len() {
+        let (id, variant) = (chunks[pos], chunks[pos + 1]);
+        let len = u16::from_le_bytes([chunks[pos + 2], chunks[pos + 3]]);
+        pos += 4;
+
+        let (small, field): (u16, &[u8]) = if variant == 0 {
+            (len, &[])
+        } else {
+            let end = (pos + len as usize).min(chunks.len());
+            let field = &chunks[pos..end];
+            pos += (len as usize + 3) & !3;
+            (0, field)
+        };
+
+        let text = || Some(id666_str(field)).filter(|t| !t.is_empty());
+        let int = || (field.len() >= 4).then(|| u32::from_le_bytes([field[0], field[1], field[2], field[3]]));
+
+        match id {
+            0x01 => if let Some(t) = text() { id666.song = t },
+            0x02 => if let Some(t) = text() { id666.game = t },
+            0x03 => if let Some(t) = text() { id666.artist = t },
+            0x04 => if let Some(t) = text() { id666.dumper = t },
+            0x05 => if let Some(d) = int().filter(|&d| d != 0) { id666.dumped = format!("{:02}/{:02}/{}", d / 100 % 100, d % 100, d / 10000) }, // YYYYMMDD
+            0x07 => if let Some(t) = text() { id666.comments = t },
+            0x10 => if let Some(t) = text() { id666.ost = t },
+            0x11 => id666.disc = Some(small as u8),
+            0x12 => id666.track = Some((small >> 8) as u8).filter(|&t| t != 0), // Upper byte is the number, lower an optional suffix char
+            0x13 => if let Some(t) = text() { id666.publisher = t },
+            0x14 => id666.copyright_year = Some(small),
+            0x30 => id666.intro_length = int().map(|t| t as f64 / TICKS_PER_SEC),
+            0x31 => id666.loop_length = int().map(|t| t as f64 / TICKS_PER_SEC),
+            _ => {}
+        }
+    }
+}
+
+/// Prints the parsed header + ID666 of the specified .spc file. Specific to this project (s2w).
+pub fn s2w_probe(loc: &Path, is_json: bool) -> anyhow::Result<()> {
+    let data = fs::read(loc).with_context(|| format!("could not read {}", loc.display()))?;
+    let header = parse_spc_header(&data).with_context(|| format!("could not parse {} as an SPC", loc.display()))?;
+
+    if is_json {
+        print_json(&header);
+        return Ok(());
+    }
+
+    println!("{}", ui(&format!("header ▶  SPC v{} ({}{})", header.version, header.id666_format.as_deref().map_or("no ID666".to_string(), |f| format!("{} ID666", f)), if header.has_xid6 { " + xid6" } else { "" })));
+
+    if let Some(tag) = header.id666 {
+        println!("{}", ui(&format!("song ▶  {}", tag.song)));
+        println!("{}", ui(&format!("game ▶  {}", tag.game)));
+        println!("{}", ui(&format!("artist ▶  {}", tag.artist)));
+        println!("{}", ui(&format!("dumper ▶  {}", tag.dumper)));
+        println!("{}", ui(&format!("dumped ▶  {}", tag.dumped)));
+        println!("{}", ui(&format!("comments ▶  {}", tag.comments)));
+        match tag.length {
+            Some(l) => println!("{}", ui(&format!("length ▶  {}s (+ {}ms fade)", l, tag.fade.unwrap_or(0)))),
+            None => println!("{}", ui("length ▶  (unset)"))
+        }
+        println!("{}", ui(&format!("emulator ▶  {}", tag.emulator)));
+
+        if header.has_xid6 {
+            println!("{}", ui(&format!("ost ▶  {}", tag.ost)));
+            println!("{}", ui(&format!("disc/track ▶  {}/{}", tag.disc.map_or("-".to_string(), |d| d.to_string()), tag.track.map_or("-".to_string(), |t| t.to_string()))));
+            println!("{}", ui(&format!("publisher ▶  {}", tag.publisher)));
+            println!("{}", ui(&format!("copyright ▶  {}", tag.copyright_year.map_or("-".to_string(), |y| y.to_string()))));
+            println!("{}", ui(&format!("intro/loop ▶  {} / {}", tag.intro_length.map_or("-".to_string(), |l| format!("{:.3}s", l)), tag.loop_length.map_or("-".to_string(), |l| format!("{:.3}s", l)))));
+        }
+    }
+
+    Ok(())
+}
+
+/// Extracts the submission ID from a bare ID, a `?id=`/`&id=` URL, or a `/download/<id>/` style path.
+pub fn smwc_id(query: &str) -> Option<&str> {
+    let query = query.trim();
+    if regex!("^[0-9]+$").is_match(query) {
+        return Some(query);
+    }
+
+    regex!("[?&]id=([0-9]+)").captures(query)
+        .or_else(|| regex!("/download/([0-9]+)(/|$)").captures(query))
+        .map(|c| c.get(1).unwrap().as_str())
+}
+
+/// Interactively searches the Music section (or takes a pasted ID/URL as-is) until a submission is picked; None if cancelled. Specific to this project (s2w).
+pub fn s2w_browse(client: &reqwest::blocking::Client, retries: u32, log: &RunLog) -> anyhow::Result<Option<String>> {
+    loop {
+        let input = match Text::new("Search SMWCentral music (or paste an ID/URL):").prompt() {
+            Ok(i) => i,
+            Err(_) => return Ok(None), // Esc/Ctrl-C just means "never mind"
+        };
+        let input = input.trim();
+
+        if smwc_id(input).is_some() {
+            return Ok(Some(input.to_string()));
+        }
+        if input.is_empty() {
+            continue;
+        }
+
+        // Backing out of the results (or finding none) goes back to the search box
+        match s2w_search(input, None, 1, client, retries, log) {
+            Ok(Some(id)) => return Ok(Some(id)),
+            Ok(None) => {}
+            Err(e) => eprintln!("{}", ui(&format!("⚠ {:#}", e))),
+        }
+    }
+}
+
+/// Fetches one page (1-based) of Music section submissions whose name matches query. Specific to this project (s2w).
+fn s2w_search_page(query: &str, page: u32, client: &reqwest::blocking::Client, retries: u32, log: &RunLog) -> anyhow::Result<SMWCPage> {
+    let url = Url::parse_with_params("https://www.smwcentral.net/ajax.php", [
+        ("a", "getsectionlist"), ("s", "smwmusic"), ("n", &page.to_string()), ("f[name]", query),
+    ]).context("invalid SMWc search URL")?;
+    log.log(&format!("GET {}", url));
+
+    let resp = s2w_get(client, url.as_str(), retries, log).context("could not connect to SMWCentral to search")?;
+    let status = resp.status();
+    let body = gunzip_lenient(&resp.bytes().context("could not read SMWC search results")?);
+    if !status.is_success() || body.trim_ascii_start().starts_with(b"<") {
+        bail!("SMWCentral search failed (HTTP {}); is the site up?", status);
+    }
+
+    serde_json::from_slice(&body).context("failed to parse SMWC search results")
+}
+
+/// Searches the Music section and lets the user pick a result, returning its ID; None if cancelled. Shows limit results per screen (default: whatever SMWC pages by), starting from listing page start_page. Specific to this project (s2w).
+pub fn s2w_search(query: &str, limit: Option<usize>, start_page: u32, client: &reqwest::blocking::Client, retries: u32, log: &RunLog) -> anyhow::Result<Option<String>> {
+    const PREV: &str = "← Previous page";
+    const NEXT: &str = "→ Next page";
+
+    let mut seen: Vec<SMWCListing> = Vec::new();
+    let mut next_page = start_page;
+    let mut last_page = start_page;
+    let mut total = 0;
+    let mut per_screen = limit.unwrap_or(0); // 0 = take SMWC's page size from the first response
+    let mut screen = 0;
+    loop {
+        // Pull listing pages until this screen is full or SMWC runs out
+        while (next_page == start_page || seen.len() < (screen + 1) * per_screen) && next_page <= last_page {
+            if next_page > start_page {
+                sleep(SEARCH_DELAY); // be polite to SMWC's public API between pages
+            }
+            let results = s2w_search_page(query, next_page, client, retries, log)?;
+            last_page = results.last_page.max(next_page);
+            total = results.total;
+            next_page += 1;
+
+            let fetched = results.data.len();
+            seen.extend(results.data);
+            if per_screen == 0 {
+                per_screen = fetched.max(1);
+            }
+            if fetched == 0 {
+                break;
+            }
+        }
+        if seen.is_empty() {
+            bail!("No Music submissions found for \"{}\"", query);
+        }
+
+        if screen * per_screen >= seen.len() && screen > 0 {
+            screen -= 1; // the "next" page came back empty after all
+            continue;
+        }
+
+        let shown = &seen[(screen * per_screen).min(seen.len())..((screen + 1) * per_screen).min(seen.len())];
+        let has_next = seen.len() > (screen + 1) * per_screen || next_page <= last_page;
+
+        let mut options: Vec<String> = shown.iter().map(|f| ui(&format!("{} — {} — {} — {} downloads",
+            f.name,
+            author_names(&f.authors, None).join(", "),
+            f.rating.map_or("(no rating)".to_string(), rating_str),
+            f.downloads,
+        ))).collect();
+        if screen > 0 {
+            options.push(ui(PREV));
+        }
+        if has_next {
+            options.push(ui(NEXT));
+        }
+
+        let of_total = if total > 0 { format!(" of {}", total) } else { String::new() };
+        let first = screen * per_screen + 1;
+        let prompt = ui(&format!("Results for \"{}\" ({}–{}{}):", query, first, first + shown.len() - 1, of_total));
+        let pick = match Select::new(&prompt, options).raw_prompt() {
+            Ok(p) => p,
+            Err(_) => return Ok(None), // Esc/Ctrl-C just means "never mind"
+        };
+
+        match shown.get(pick.index) {
+            Some(f) => return Ok(Some(f.id.to_string())),
+            None if pick.value == ui(PREV) => screen -= 1,
+            None => screen += 1,
+        }
+    }
+}
+
+/// Prints the magic-number filetype of each specified file (directories are expanded one level). Specific to this project (s2w).
+pub fn s2w_identify(paths: &[PathBuf], report_unidentified: bool, is_json: bool) -> anyhow::Result<()> {
+    let results = identify(paths, report_unidentified)?;
+    if is_json {
+        print_json(&results);
+        return Ok(());
+    }
+
+    for r in results {
+        match (r.variant, r.prefix) {
+            (Some(v), _) => println!("{}", ui(&format!("{} ▶  {}", r.path.display(), v))),
+            (None, Some(p)) => println!("{}", ui(&format!("{} ▶  (unidentified) [{}]", r.path.display(), p))),
+            (None, None) => println!("{}", ui(&format!("{} ▶  (unidentified)", r.path.display()))),
+        }
+    }
+
+    Ok(())
+}
+
+/// Magic-number filetype of each file (directories expanded one level, sorted), plus the hex start of unidentified ones if asked.
+fn identify(paths: &[PathBuf], report_unidentified: bool) -> anyhow::Result<Vec<Identified>> {
+    let mut files = vec![];
+    for path in paths {
+        if path.is_dir() {
+            let mut entries: Vec<PathBuf> = fs::read_dir(path)
+                .with_context(|| format!("could not read directory {}", path.display()))?
+                .filter_map(Result::ok)
+                .map(|e| e.path())
+                .filter(|p| p.is_file())
+                .collect();
+            entries.sort();
+            files.extend(entries);
+        } else {
+            files.push(path.clone());
+        }
+    }
+
+    files.into_iter().map(|path| {
+        let variant = magictype_from_path(&path)
+            .with_context(|| format!("could not read {}", path.display()))?
+            .map(|f| f.as_ref().to_string());
+        let prefix = (variant.is_none() && report_unidentified).then(|| {
+            let mut head = vec![];
+            let _ = fs::File::open(&path).and_then(|f| io::Read::read_to_end(&mut io::Read::take(f, 16), &mut head));
+            hex_str(&head, " ").to_uppercase()
+        });
+
+        Ok(Identified { path, variant, prefix })
+    }).collect()
+}
+
+/// Runs each batch query through run, reporting failures (and panics, caught so one bad item can't end the batch) and carrying on.
+/// Specific to this project (s2w).
+pub fn s2w_batch(queries: &[String], opts: &RunOpts, json_lines: bool, log: &RunLog, run: impl Fn(&str) -> anyhow::Result<RunOutcome>) -> Vec<RunReport> {
+    let mut reports = vec![];
+    for (i, query) in queries.iter().enumerate() {
+        if !opts.suppress_output {
+            println!("{}", ui(&format!("\x1B[38;2;143;122;238m▶ ({}/{}) {}\x1B[0m", i + 1, queries.len(), query)));
+        }
+        // One bad item shouldn't take the rest of the batch down with it
+        let res = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| run(query))) {
+            Ok(res) => res,
+            Err(payload) => {
+                ow_end();
+                let msg = payload.downcast_ref::<&str>().map(|s| s.to_string()).or_else(|| payload.downcast_ref::<String>().cloned()).unwrap_or_else(|| "unknown panic".to_string());
+                log.log(&format!("PANIC {}: {}", query, msg));
+                eprintln!("{}", ui(&format!("\x1B[38;2;255;80;80m✘ {} panicked: {}\x1B[0m", query, msg)));
+                reports.push(RunReport::panicked(query, &msg));
+                continue;
+            }
+        };
+        ow_end();
+        match &res {
+            Ok(RunOutcome::Saved(result)) if json_lines => print_json_line(result),
+            Ok(RunOutcome::Planned(plan)) if json_lines => print_json_line(plan),
+            Err(e) => {
+                log.log(&format!("ERROR {}: {:#}", query, e));
+                eprintln!("{}", ui(&format!("\x1B[38;2;255;80;80m✘ {}: {:#}\x1B[0m", query, e)));
+            }
+            _ => {}
+        }
+        reports.push(RunReport::new(query, &res));
+    }
+    reports
+}
+
+/// Runs the whole pipeline (fetch → banner → download → extract → render → convert → tag) for one query. Specific to this project (s2w).
+pub fn s2w_run(query: &str, opts: &RunOpts, client: &reqwest::blocking::Client, log: &RunLog) -> anyhow::Result<RunOutcome> {
+    let id = smwc_id(query).with_context(|| format!("could not parse SMWC ID from query \"{}\"", query))?;
+    let mut ca_data = opts.cover.clone();
+
+    // Everything past here needs the submission itself, so stop at what's known without it
+    if opts.dry_run {
+        let out_dir = opts.output_dir.clone().unwrap_or_else(|| PathBuf::from("."));
+        let conv = match &opts.format {
+            _ if opts.download_only => "none (raw .spc files)".to_string(),
+            _ if opts.list_tracks => "none (listing tracks only)".to_string(),
+            Some(f) if opts.is_format_valid => match Encoder::pick(f, which(tool("sox")).is_ok(), which(tool("ffmpeg")).is_ok()) {
+                Some(encoder) => format!("{} via {}", f, encoder.name()),
+                None => format!("{} (no encoder installed for it!)", f),
+            },
+            _ if opts.is_skip => "wav (no conversion)".to_string(),
+            _ => "asked after rendering".to_string(),
+        };
+        let cover = match (&opts.coverart, opts.auto_cover) {
+            (Some(ca), _) => ca.clone(),
+            (None, true) => "best of the submission's images".to_string(),
+            (None, false) if opts.is_interactive && !opts.is_yes => "picked from the submission's images".to_string(),
+            _ => "none".to_string(),
+        };
+
+        let plan = DryRunPlan {
+            id: id.parse().unwrap_or_default(),
+            url: api_url(id),
+            output: opts.output.as_ref().unwrap_or(&out_dir.join("<track>")).display().to_string(),
+            format: conv,
+            album: opts.tags.album.clone().unwrap_or_else(|| "(submission name)".to_string()),
+            cover,
+            stats: opts.tags.is_stats,
+        };
+
+        // --json gets the plan as a document instead (printed by the caller)
+        if !opts.json {
+            println!("{}", ui(&format!("#{} ▶  {}", plan.id, plan.url)));
+            println!("{}", ui(&format!("  output ▶  {}", plan.output)));
+            println!("{}", ui(&format!("  format ▶  {}", plan.format)));
+            println!("{}", ui(&format!("  album ▶  {}", plan.album)));
+            println!("{}", ui(&format!("  cover ▶  {}", plan.cover)));
+            if plan.stats {
+                println!("{}", ui("  stats ▶  downloads, rating, featured"));
+            }
+        }
+        log.log(&format!("Dry run for #{}", id));
+        return Ok(RunOutcome::Planned(plan));
+    }
+
+    let mut file = s2w_fetch(id, client, opts.retries, log, opts.no_progress)?;
+
+    // Superseded rips: offer the newest version, following the chain (but never around a loop)
+    let mut seen_ids = HashSet::from([file.id]);
+    while let Some(newer) = file.obsoleted_by {
+        if !seen_ids.insert(newer) {
+            eprintln!("{}", ui(&format!("⚠ Obsoletion chain loops back to #{}; keeping #{}", newer, file.id)));
+            break;
+        }
+
+        let follow = if opts.is_interactive {
+            Confirm::new(&format!("This file is obsoleted by #{}. Download the newer version instead?", newer)).with_default(true).prompt().ok()
+        } else {
+            None
+        };
+        match follow {
+            Some(true) => {
+                log.log(&format!("#{} obsoleted by #{}, following", file.id, newer));
+                file = s2w_fetch(&newer.to_string(), client, opts.retries, log, opts.no_progress)?;
+            }
+            Some(false) => break,
+            None => {
+                eprintln!("{}", ui(&format!("⚠ #{} is obsoleted by #{}; downloading it anyway", file.id, newer)));
+                break;
+            }
+        }
+    }
+
+    if opts.dedup_authors {
+        dedup_authors(&mut file.authors);
+    }
+
+    // Banner and title tag both read file.name, so patch a blank one here once
+    if let Some(name) = placeholder_name(&file) {
+        file.name = name;
+        eprintln!("Submission has no name; using \"{}\"", ui(&file.name));
+        log.log(&format!("Blank submission name, substituted \"{}\"", file.name));
+    }
+
+    // Tag filters decide before anything is shown or downloaded
+    if let Some(why) = tag_mismatch(&file.tags, &opts.require_tag, &opts.exclude_tag) {
+        eprintln!("{}", ui(&format!("⏭ Skipping #{} \"{}\": {}", file.id, file.name, why)));
+        log.log(&format!("Skipped #{} ({})", file.id, why));
+        return Ok(RunOutcome::Skipped { id: file.id, name: file.name, why });
+    }
+
+    if opts.preview_description {
+        print!("{}", ui(&s2w_description(&file)));
+        return Ok(RunOutcome::Shown);
+    }
+
+    if opts.auto_cover && ca_data.is_none() {
+        ca_data = file.images.as_deref().and_then(|imgs| s2w_auto_cover(imgs, client, opts.retries, log));
+    }
+
+    if !opts.suppress_output {
+        s2w_banner(&file, opts.style);
+    }
+
+    // No cover given: offer the submission's own images (only worth asking if something will get tagged)
+    let is_tagged = !(opts.download_only || opts.list_tracks);
+    if ca_data.is_none() && is_tagged && opts.is_interactive && !opts.is_yes {
+        if let Some(imgs) = file.images.as_deref().filter(|i| !i.is_empty()) {
+            let mut options = vec!["(no cover art)".to_string()];
+            options.extend(imgs.iter().cloned());
+            let pick = Select::new("Embed one of the submission's images as cover art?", options).with_starting_cursor(1).raw_prompt().context("No choice!")?;
+            if pick.index > 0 {
+                match s2w_image(&imgs[pick.index - 1], client, opts.retries, log) {
+                    Ok(cover) => ca_data = Some(cover),
+                    Err(e) => eprintln!("{}", ui(&format!("⚠ Skipping cover art: {:#}", e))),
+                }
+            }
+        }
+    }
+
+    // The SPC bundles what it plays, but a pack's loose banks are only as faithful as the set shipped with it
+    if let SampleUse::Custom(pack) = SampleUse::parse(&file.raw_fields.samples) {
+        eprintln!("{}", ui(&format!("\x1B[38;2;255;200;80m⚠ This port uses custom samples{}; playback fidelity depends on the original sample set\x1B[0m", pack.map(|p| format!(" ({})", p)).unwrap_or_default())));
+    }
+
+    let mut kept_files: Vec<PathBuf> = vec![];
+    // Rendered WAVs that were already in the output directory; those belong to the user, so they're never removed
+    let mut reused: Vec<PathBuf> = vec![];
+
+    let out_dir = opts.output_dir.clone().unwrap_or_else(|| PathBuf::from("."));
+    fs::create_dir_all(&out_dir).with_context(|| format!("Could not create output directory {}", out_dir.display()))?;
+
+    // Intermediates (zip, .spc, .wav) live in a private dir so runs sharing an output directory can't collide; only finished files leave it
+    let work_dir = tempfile::Builder::new()
+        .prefix(".smwc2wav-")
+        .tempdir_in(&out_dir)
+        .context("Could not create working directory")?;
+    let work = fs::canonicalize(work_dir.path())?;
+
+    // Only names we derive get transliterated; an explicit --output is taken as-is
+    let derive_name = |p: PathBuf| if opts.ascii_filenames { ascii_filename(&p) } else { p };
+    // Anything from the working directory keeps its place within the archive (only ever nested with --keep-dirs)
+    let publish = |p: &Path| {
+        let dest = derive_name(out_dir.join(p.strip_prefix(&work).unwrap_or(Path::new(p.file_name().unwrap()))));
+        if let Some(parent) = dest.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        dest
+    };
+    // Every final output (track, raw SPC, kept extra) carries the submission time under --preserve-time
+    let stamp = |p: &Path| -> anyhow::Result<()> {
+        if opts.preserve_time {
+            filetime::set_file_mtime(p, FileTime::from_unix_time(file.time as i64, 0))
+                .with_context(|| format!("Could not set modification time of {}", p.display()))?;
+        }
+        Ok(())
+    };
+
+    // Every rendered track, alongside the SPC's own tag (if any)
+    let tracks: Vec<(PathBuf, Option<ID666>)> = if let Some(wav) = &opts.from_wav {
+        vec![(wav.clone(), None)]
+    } else {
+        if !opts.is_yes {
+            print!("Confirm download...");
+            io::stdout().flush().unwrap();
+            io::stdin().read_line(&mut String::new()).context("Could not read confirmation")?;
+        }
+        let zip_path = work.join(format!("{}.zip", file.id));
+        let zip_fname = zip_path.to_str().context("Working directory must be valid UTF-8")?;
+        // Only the zip is reused; download_url always comes from the fetch above, so an expiring link can't go stale in the cache
+        let cached = if opts.no_cache { None } else { s2w_cache_hit(&file) };
+        if !opts.suppress_output {
+            ow_print(&format!("Downloading zip (1/3){}", if cached.is_some() { " (cached)" } else { "" }));
+        }
+
+        // Extraction deletes the zip, so work on a copy of the cached one
+        if let Some(cached) = &cached {
+            fs::copy(cached, &zip_path).with_context(|| format!("Could not copy cached {}", cached.display()))?;
+            log.log(&format!("Cache hit {}", cached.display()));
+        }
+
+        // A short read means a truncated zip, which would only fail confusingly at extraction; SMWC knows the real size
+        let mut attempt = 0;
+        while cached.is_none() {
+            let dl_bytes = s2w_download(&file.download_url, zip_fname, client, opts.retries, log, opts.no_progress, opts.no_delay)?;
+            if file.size == 0 || dl_bytes == file.size as u64 {
+                break;
+            }
+            if attempt >= opts.retries {
+                bail!("Download of {} is {} bytes, but SMWC reports {} (truncated?)", file.download_url, dl_bytes, file.size);
+            }
+
+            attempt += 1;
+            eprintln!("{}", ui(&format!("⚠ Got {} of {} bytes, downloading again ({}/{})", dl_bytes, file.size, attempt, opts.retries)));
+            log.log(&format!("Size mismatch ({} vs {}), retry {}/{}", dl_bytes, file.size, attempt, opts.retries));
+        }
+
+        // A cache that can't be written just means downloading again next time
+        if cached.is_none() && !opts.no_cache {
+            if let Err(e) = s2w_cache_store(&file, &zip_path) {
+                log.log(&format!("Not cached: {:#}", e));
+            }
+        }
+
+        // Just a peek inside; the zip goes away with the working directory
+        if opts.list_tracks {
+            let listed = s2w_list(zip_fname)?;
+            if !opts.suppress_output {
+                ow_done(&format!("\x1B[38;2;41;255;188m{} track(s) in #{} ✔\x1B[0m", listed.len(), file.id));
+            }
+            for (i, (name, size)) in listed.iter().enumerate() {
+                if opts.quiet {
+                    println!("{}", name.display());
+                } else {
+                    println!("{}", ui(&format!("  {:>2}. {} ({})", i + 1, name.display(), HumanBytes(*size))));
+                }
+            }
+            return Ok(RunOutcome::Shown);
+        }
+
+        if !opts.suppress_output {
+            ow_print("Extracting zip (2/3)");
+        }
+        let (extracted, skipped, sources) = s2w_extract(zip_fname, &work, &opts.keep_ext, opts.keep_dirs, log, opts.no_progress, opts.no_delay)?;
+        let (mut spc_files, extra_files): (Vec<PathBuf>, Vec<PathBuf>) = extracted
+            .into_iter()
+            .partition(|p| p.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("spc")));
+        let mut discs = s2w_order_discs(&mut spc_files, &sources);
+        kept_files = extra_files;
+
+        if spc_files.is_empty() {
+            bail!("{}", no_spc_reason(&skipped));
+        }
+
+        // Raw SPCs are all that's wanted; skip spc2wav/SoX/tagging entirely
+        if opts.download_only {
+            let mut saved = vec![];
+            for spc in &spc_files {
+                let dest = publish(spc);
+                move_file(spc, &dest).with_context(|| format!("Could not move {}", spc.display()))?;
+                stamp(&dest)?;
+                log.log(&format!("Saved {}", dest.display()));
+                if opts.quiet {
+                    println!("{}", dest.display());
+                }
+                saved.push(dest);
+            }
+            if !opts.suppress_output {
+                ow_done(&format!("\x1B[38;2;41;255;188m{} .spc file(s) saved ✔\x1B[0m", saved.len()));
+            }
+
+            if opts.open_folder {
+                if let Some(spc) = saved.first() {
+                    s2w_reveal(spc);
+                }
+            }
+            return Ok(RunOutcome::Saved(Box::new(RunResult { outputs: saved.iter().map(|p| p.to_string_lossy().to_string()).collect(), file })));
+        }
+
+        // --no-album-mode: a pack gets the single-track treatment, so only one of its SPCs is converted
+        if opts.album_mode == Some(false) && spc_files.len() > 1 {
+            let pick = s2w_pick_track(&spc_files, opts.is_interactive && !opts.is_yes)?;
+            log.log(&format!("Album mode off, converting only {}", pick.display()));
+            spc_files = vec![pick];
+            discs = None;
+        }
+
+        if opts.output.is_some() && spc_files.len() > 1 {
+            bail!("--output names a single file, but this pack has {} tracks!", spc_files.len());
+        }
+
+        // A track a previous run already rendered (e.g. --no-convert) would render the same again, so convert from that WAV instead
+        let prior_wavs: Vec<Option<PathBuf>> = spc_files.iter()
+            .map(|spc| publish(&spc.with_extension("wav")))
+            .map(|wav| (!opts.force && magictype_from_path(&wav).ok().flatten() == Some(FileType::WAV)).then_some(wav))
+            .collect();
+        for wav in prior_wavs.iter().flatten() {
+            log.log(&format!("Reusing {} (--force renders it again)", wav.display()));
+            if !opts.suppress_output {
+                PROGRESS.suspend(|| println!("{}", ui(&format!("⏭ Reusing {} (--force renders it again)", wav.display()))));
+            }
+        }
+        let to_render: Vec<PathBuf> = spc_files.iter().zip(&prior_wavs).filter(|(_, w)| w.is_none()).map(|(s, _)| s.clone()).collect();
+
+        if !opts.suppress_output {
+            ow_print(&format!("Converting spc → wav (3/3){}", if to_render.len() > 1 { format!(" ×{}", to_render.len()) } else { String::new() }));
+        }
+
+        // Grab each SPC's own tag before spc2wav consumes the file
+        let is_album = opts.album_mode.unwrap_or(spc_files.len() > 1);
+        let spc_tags = s2w_track_tags(&spc_files, discs.as_deref(), is_album, opts.track_title_style);
+
+        let mut rendered = if to_render.is_empty() { vec![] } else { s2w_conv(&to_render, opts.spc2wav_outdir.as_deref(), log, opts.no_progress, opts.no_delay, opts.jobs)? }.into_iter();
+        let wavs: Vec<PathBuf> = prior_wavs.into_iter().map(|w| w.or_else(|| rendered.next()).unwrap()).collect();
+        reused = wavs.iter().filter(|w| !w.starts_with(&work)).cloned().collect();
+        let wav_bytes: u64 = wavs.iter().map(|w| fs::metadata(w).map(|m| m.len())).sum::<Result<u64, _>>()?;
+        if !opts.suppress_output {
+            ow_done(&format!("\x1B[38;2;41;255;188m{} of 16-bit goodness saved ✔\x1B[0m", HumanBytes(wav_bytes)));
+        }
+
+        wavs.into_iter().zip(spc_tags).collect()
+    };
+
+    // Only the SPC's tag knows the track length, so this can't be checked up front
+    for (wav, spc_tag) in &tracks {
+        if let (Some(t), Some(len)) = (opts.trim_start, spc_tag.as_ref().and_then(|t| t.length)) {
+            if t >= len as f64 {
+                bail!("--trim-start ({}s) is not shorter than {} ({}s)!", t, wav.display(), len);
+            }
+        }
+    }
+
+    let has_sox: bool = which(tool("sox")).is_ok();
+    let has_ffmpeg: bool = which(tool("ffmpeg")).is_ok();
+
+    // One conversion decision (format + encoder) for the whole pack
+    let conv_format: Option<(String, Encoder)> = if opts.is_skip || opts.no_convert {
+        None
+    } else if !has_sox && !has_ffmpeg {
+        eprintln!("{}", ui("⚠ Neither SoX nor ffmpeg found; keeping the .wav"));
+        None
+    } else {
+        // A format from --format/--output already states the intent to convert, so don't second-guess it
+        let is_conv = if opts.is_format_valid {
+            Ok(true)
+        } else {
+            Confirm::new(&format!("{} detected. Convert audio format?", if has_sox { "SoX" } else { "ffmpeg" })).prompt()
+        };
+
+        if is_conv.context("No choice!")? {
+            let conv_format = if opts.is_format_valid {
+                opts.format.clone().unwrap()
+            } else {
+                Select::new("Select format:", if has_ffmpeg { FFMPEG_FORMATS.to_vec() } else { SOX_FORMATS.to_vec() }).prompt().context("No format chosen!")?.to_string()
+            };
+            let encoder = Encoder::pick(&conv_format, has_sox, has_ffmpeg).with_context(|| format!("Converting to .{} needs ffmpeg, which isn't installed!", conv_format))?;
+            Some((conv_format, encoder))
+        } else {
+            None
+        }
+    };
+
+    let conv_names: Vec<Option<PathBuf>> = tracks.iter().map(|(wav, _)| conv_format.as_ref().map(|(conv_format, _)| match &opts.output {
+        Some(out) => out.clone(),
+        // A handed-in WAV converts in place unless told where to put it
+        None if opts.from_wav.is_some() && opts.output_dir.is_none() => derive_name(wav.with_extension(conv_format)),
+        None => publish(&wav.with_extension(conv_format)),
+    })).collect();
+
+    // Encoding is the slow part, so it runs (--jobs at a time) ahead of the in-order publish + tag pass below
+    if let Some((_, encoder)) = &conv_format {
+        // Single tracks overwrite the prompts like before; packs list one line per track instead
+        if tracks.len() == 1 && !opts.suppress_output {
+            ow_print(&format!("\x1B[38;2;143;122;238mProcessing via {}...\x1B[0m", encoder.name()));
+        }
+        par_map(&tracks, opts.jobs, |i, (wav, _)| {
+            s2w_encode(*encoder, wav, conv_names[i].as_ref().unwrap(), opts.audio, opts.trim_start, opts.peak_normalize_to, log)
+        }).into_iter().collect::<anyhow::Result<()>>()?;
+    }
+
+    let mut out_paths = vec![];
+    for (i, ((wav, spc_tag), conv_name)) in tracks.iter().zip(conv_names).enumerate() {
+        let out_path = match (&conv_format, conv_name) {
+            (Some((_, encoder)), Some(conv_name)) => {
+                let wav_size = fs::metadata(wav)?.len();
+
+                let done = format!("\x1B[38;2;41;255;188m{} → {} converted via {} ✔\x1B[0m", HumanBytes(wav_size), HumanBytes(fs::metadata(&conv_name).with_context(|| format!("{} produced no {}", encoder.name(), conv_name.display()))?.len()), encoder.name());
+                if !opts.suppress_output {
+                    if tracks.len() == 1 {
+                        ow_done(&done);
+                    } else {
+                        println!("{}", ui(&format!("({}/{}) {}", i + 1, tracks.len(), done)));
+                    }
+                }
+
+                // Never delete a WAV the user handed us (or one left by an earlier run)
+                if opts.from_wav.is_none() && !reused.contains(wav) {
+                    fs::remove_file(wav).with_context(|| format!("Could not remove {}", wav.display()))?;
+                }
+
+                conv_name
+            }
+            _ => match &opts.output {
+                Some(out) if opts.is_skip && reused.contains(wav) => {
+                    fs::copy(wav, out).with_context(|| format!("Could not copy .wav file to {}", out.display()))?;
+                    out.clone()
+                }
+                Some(out) if opts.is_skip => {
+                    move_file(wav, out).with_context(|| format!("Could not move .wav file to {}", out.display()))?;
+                    out.clone()
+                }
+                _ => wav.clone(),
+            },
+        };
+
+        // Whatever is still in the working directory (unconverted WAV) is final output too
+        let out_path = if out_path.starts_with(&work) {
+            let dest = publish(&out_path);
+            move_file(&out_path, &dest).with_context(|| format!("Could not move .wav file to {}", dest.display()))?;
+            dest
+        } else {
+            out_path
+        };
+
+        // A tagging hiccup shouldn't cost the conversion; the audio stays as it is
+        if let Err(e) = s2w_tag(&out_path, &file, &opts.tags, ca_data.as_ref(), spc_tag.as_ref()) {
+            log.log(&format!("Tagging {} failed: {:#}", out_path.display(), e));
+            eprintln!("{}", ui(&format!("⚠ {} is missing tags: {:#}", out_path.display(), e)));
+        }
+
+        stamp(&out_path)?;
+
+        log.log(&format!("Saved {}", out_path.display()));
+        if opts.quiet {
+            println!("{}", out_path.display());
+        }
+        out_paths.push(out_path);
+    }
+
+    // Extra audio kept via --keep-ext skips spc2wav, but goes through the same conversion + tagging
+    for extra in &kept_files {
+        let (target, encoder) = match &conv_format {
+            Some((conv_format, encoder)) => (publish(&extra.with_extension(conv_format)), Some(*encoder)),
+            None => (publish(extra), None),
+        };
+
+        if let (Some(encoder), true) = (encoder, extra.extension() != target.extension()) {
+            s2w_encode(encoder, extra, &target, AudioOpts { duration: None, fade: None, ..opts.audio }, None, opts.peak_normalize_to, log)?;
+            fs::remove_file(extra).with_context(|| format!("Could not remove {}", extra.display()))?;
+        } else {
+            move_file(extra, &target).with_context(|| format!("Could not move {}", extra.display()))?;
+        }
+
+        if let Err(e) = s2w_tag(&target, &file, &opts.tags, ca_data.as_ref(), None) {
+            log.log(&format!("Tagging {} failed: {:#}", target.display(), e));
+            eprintln!("{}", ui(&format!("⚠ {} is missing tags: {:#}", target.display(), e)));
+        }
+        stamp(&target)?;
+        log.log(&format!("Saved {}", target.display()));
+        if opts.quiet {
+            println!("{}", target.display());
+        }
+        out_paths.push(target);
+    }
+
+    if opts.open_folder {
+        if let Some(out_path) = out_paths.first() {
+            s2w_reveal(out_path);
+        }
+    }
+
+    Ok(RunOutcome::Saved(Box::new(RunResult { outputs: out_paths.iter().map(|p| p.to_string_lossy().to_string()).collect(), file })))
+}
+
+/// Fetches a submission's metadata with a default client (3 retries, nothing logged).
+pub fn fetch_metadata(id: &str) -> anyhow::Result<SMWCFile> {
+    s2w_fetch(id, &reqwest::blocking::Client::new(), 3, &RunLog::open(None, false)?, true)
+}
+
+/// Downloads url to dest without drawing progress, returning the bytes written.
+pub fn download(url: &str, dest: &Path) -> anyhow::Result<u64> {
+    let dest = dest.to_str().context("Download destination must be valid UTF-8")?;
+    s2w_download(url, dest, &reqwest::blocking::Client::new(), 3, &RunLog::open(None, false)?, true, true)
+}
+
+/// Extracts every .spc in the zip at loc into dest (deleting the zip), returning their paths.
+pub fn extract_spcs(loc: &Path, dest: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let loc = loc.to_str().context("Archive path must be valid UTF-8")?;
+    Ok(s2w_extract(loc, dest, &[], false, &RunLog::open(None, false)?, true, true)?.0)
+}
+
+/// Renders an .spc to .wav via spc2wav (consuming the .spc), returning the WAV's path.
+pub fn convert_spc(loc: &Path) -> anyhow::Result<PathBuf> {
+    s2w_spc2wav(loc, None, &RunLog::open(None, false)?)
+}
+
+/// Tags an audio file with a submission's metadata (+ the SPC's own tag, if known).
+pub fn tag_file(loc: &Path, file: &SMWCFile, spc_tag: Option<&ID666>) -> anyhow::Result<()> {
+    s2w_tag(loc, file, &TagOpts::default(), None, spc_tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::io::Cursor;
+
+    thread_local! {
+        /// This test's mock SMWC API (see api_root), if it set one up.
+        pub(super) static API_ROOT: RefCell<Option<String>> = const { RefCell::new(None) };
+    }
+
+    /// A getfile response with just the fields SMWCFile needs, authors as given.
+    fn file_json(authors: Value) -> Value {
+        serde_json::json!({
+            "id": 12345, "section": "smwmusic", "name": "Forest Theme", "time": 1700000000,
+            "authors": authors, "submitter": { "id": 7, "name": "Uploader" },
+            "tags": ["forest"], "images": null, "rating": 4.5, "size": 1024, "downloads": 99,
+            "download_url": "https://dl.smwcentral.net/12345/forest.zip", "obsoleted_by": null,
+            "raw_fields": { "featured": 1, "source": "Original" }
+        })
+    }
+
+    fn zip_of(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(vec![]));
+        for (name, data) in entries {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    /// [id, type, len] + data padded to 4, as in an xid6 chunk.
+    fn xid6_sub(id: u8, variant: u8, data: &[u8], small: u16) -> Vec<u8> {
+        let len = if variant == 0 { small } else { data.len() as u16 };
+        let mut sub = vec![id, variant];
+        sub.extend(len.to_le_bytes());
+        sub.extend(data);
+        sub.resize(sub.len().next_multiple_of(4), 0);
+        sub
+    }
+
+    fn xid6_of(subs: &[Vec<u8>]) -> Vec<u8> {
+        let body = subs.concat();
+        let mut xid6 = b"xid6".to_vec();
+        xid6.extend((body.len() as u32).to_le_bytes());
+        xid6.extend(body);
+        xid6
+    }
+
+    #[test]
+    fn strip_html_decodes_and_collapses() {
+        assert_eq!(strip_html("Line one<br>Line two<BR />\nthree"), "Line one\nLine two\nthree");
+        assert_eq!(strip_html("<b>Bold</b> &amp; <i>it</i> &#65;&#x42; &foo;"), "Bold & it AB &foo;");
+        assert_eq!(strip_html("&amp;lt;"), "&lt;");
+        assert_eq!(strip_html("a\r\n\r\n\r\n\r\nb  \nc"), "a\n\nb\nc");
+    }
+
+    #[test]
+    fn strclamp_wraps_at_spaces() {
+        assert_eq!(strclamp("hello world", 5), "hello\nworld\n");
+        assert_eq!(strclamp("one two three", 7), "one two\nthree\n");
+        assert_eq!(strclamp("abcdefgh", 3), "abc\ndef\ngh\n");
+        assert_eq!(strclamp("a\nb", 10), "a\nb\n");
+    }
+
+    #[test]
+    fn strclamp_handles_multibyte() {
+        let input = "Ünïcödé — 日本語のタイトル and more àccents";
+        for max_len in 1..20 {
+            let out = strclamp(input, max_len);
+            assert!(out.ends_with('\n'));
+            // Double-width chars can't span lines, so a 1-column line still fits one of them
+            assert!(out.lines().all(|l| l.width() <= max_len.max(2)), "{:?} at {}", out, max_len);
+            assert_eq!(out.replace(['\n', ' '], ""), input.replace(' ', ""));
+        }
+    }
+
+    #[test]
+    fn magic_patterns_all_compile() {
+        assert_eq!(MAGIC_REGEXES.len(), FileType::iter().count());
+    }
+
+    #[test]
+    fn magictype_detects_heic() {
+        let heic = [0x00, 0x00, 0x00, 0x18, b'f', b't', b'y', b'p', b'h', b'e', b'i', b'c', 0x00, 0x00, 0x00, 0x00, b'm', b'i', b'f', b'1', b'h', b'e', b'i', b'c'];
+        assert_eq!(magictype(&heic), Some(FileType::HEIC));
+    }
+
+    #[test]
+    fn magictype_detects_zip() {
+        assert_eq!(magictype(&zip_of(&[("Forest Theme/forest.spc", b"SNES-SPC700")])), Some(FileType::ZIP));
+        assert_eq!(magictype(&zip_of(&[])), Some(FileType::ZIP));
+        assert_eq!(magictype(&[0x50, 0x4B, 0x07, 0x08, 0, 0, 0, 0]), Some(FileType::ZIP));
+    }
+
+    #[test]
+    fn magictype_detects_au() {
+        assert_eq!(magictype(b".snd\x00\x00\x00\x18\x00\x00\x00\x00"), Some(FileType::AU));
+    }
+
+    #[test]
+    fn magictype_from_path_matches_magictype() {
+        let dir = tempfile::tempdir().unwrap();
+        let loc = dir.path().join("music.zip");
+        let data = zip_of(&[("a.spc", b"x")]);
+        fs::write(&loc, &data).unwrap();
+        assert_eq!(magictype_from_path(&loc).unwrap(), magictype(&data));
+    }
+
+    #[test]
+    fn de_authors_takes_objects() {
+        let file: SMWCFile = serde_json::from_value(file_json(serde_json::json!([{ "id": 1, "name": "Alice" }, { "id": 2, "name": "Bob" }]))).unwrap();
+        assert_eq!(file.authors.iter().map(|a| (a.id, a.name.as_str())).collect::<Vec<_>>(), [(1, "Alice"), (2, "Bob")]);
+    }
+
+    #[test]
+    fn de_authors_takes_a_flat_string() {
+        let file: SMWCFile = serde_json::from_value(file_json(serde_json::json!("Alice, Bob ,, Carol"))).unwrap();
+        assert_eq!(file.authors.iter().map(|a| (a.id, a.name.as_str())).collect::<Vec<_>>(), [(0, "Alice"), (0, "Bob"), (0, "Carol")]);
+    }
+
+    #[test]
+    fn zero_authors_fall_back() {
+        let file: SMWCFile = serde_json::from_value(file_json(serde_json::json!([]))).unwrap();
+        assert!(file.authors.is_empty());
+        assert_eq!(author_names(&file.authors, file.submitter.as_ref()), ["Uploader"]);
+        assert_eq!(author_names(&[], None), ["Unknown"]);
+        assert_eq!(author_names(&[SMWCUser { id: 1, name: "  ".to_string() }], None), ["Unknown"]);
+
+        let file: SMWCFile = serde_json::from_value(file_json(Value::Null)).unwrap();
+        assert!(file.authors.is_empty());
+    }
+
+    #[test]
+    fn de_rating_is_lenient() {
+        let rating = |r: Value| {
+            let mut file = file_json(serde_json::json!([]));
+            file["rating"] = r;
+            serde_json::from_value::<SMWCFile>(file).unwrap().rating
+        };
+        assert_eq!(rating(serde_json::json!(4.5)), Some(4.5));
+        assert_eq!(rating(serde_json::json!("3.25")), Some(3.25));
+        assert_eq!(rating(serde_json::json!(null)), None);
+        assert_eq!(rating(serde_json::json!(-1)), None);
+        assert_eq!(rating(serde_json::json!("n/a")), None);
+        assert_eq!(rating(serde_json::json!("NaN")), None);
+    }
+
+    #[test]
+    fn de_flag_is_lenient() {
+        let featured = |f: Value| serde_json::from_value::<SMWCAudioFields>(serde_json::json!({ "featured": f })).unwrap().featured;
+        assert!(featured(serde_json::json!(true)));
+        assert!(featured(serde_json::json!(1)));
+        assert!(featured(serde_json::json!(" Yes ")));
+        assert!(!featured(serde_json::json!("0")));
+        assert!(!featured(serde_json::json!(0)));
+        assert!(!featured(serde_json::json!(null)));
+        assert!(!featured(serde_json::json!([1])));
+    }
+
+    #[test]
+    fn apply_xid6_overlays_subchunks() {
+        let xid6 = xid6_of(&[
+            xid6_sub(0x01, 1, b"Forest\0", 0),
+            xid6_sub(0x11, 0, &[], 2),
+            xid6_sub(0x12, 0, &[], 3 << 8),
+            xid6_sub(0x13, 1, b"Nintendo\0", 0),
+            xid6_sub(0x31, 4, &128000u32.to_le_bytes(), 0),
+        ]);
+
+        let mut tag = ID666 { song: "Old".to_string(), game: "Kept".to_string(), ..Default::default() };
+        apply_xid6(&mut tag, &xid6);
+        assert_eq!(tag.song, "Forest");
+        assert_eq!(tag.game, "Kept");
+        assert_eq!(tag.disc, Some(2));
+        assert_eq!(tag.track, Some(3));
+        assert_eq!(tag.publisher, "Nintendo");
+        assert_eq!(tag.loop_length, Some(2.0));
+    }
+
+    #[test]
+    fn apply_xid6_ignores_truncation() {
+        let mut tag = ID666 { song: "Old".to_string(), ..Default::default() };
+        apply_xid6(&mut tag, b"xid6\x10");
+        assert_eq!(tag.song, "Old");
+
+        // Size claims more than is there, and the last sub-chunk's data runs off the end
+        let mut xid6 = xid6_of(&[xid6_sub(0x12, 0, &[], 5 << 8), xid6_sub(0x01, 1, b"Cut off here", 0)]);
+        xid6[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        xid6.truncate(xid6.len() - 6);
+        apply_xid6(&mut tag, &xid6);
+        assert_eq!(tag.track, Some(5));
+        assert_eq!(tag.song, "Cut of");
+    }
+
+    #[test]
+    fn parse_spc_header_reads_xid6() {
+        let mut spc = b"SNES-SPC700 Sound File Data v0.30\x1A\x1A".to_vec();
+        spc.resize(0x10200, 0);
+        spc[0x23] = 26;
+        spc[0x24] = 30;
+        spc[0x2E..0x34].copy_from_slice(b"Base 1");
+        spc[0xA9..0xAC].copy_from_slice(b"120");
+        spc.extend(xid6_of(&[xid6_sub(0x01, 1, b"Extended\0", 0), xid6_sub(0x12, 0, &[], 7 << 8)]));
+
+        let header = parse_spc_header(&spc).unwrap();
+        assert_eq!(header.id666_format.as_deref(), Some("text"));
+        assert!(header.has_xid6);
+        let tag = header.id666.unwrap();
+        assert_eq!(tag.song, "Extended");
+        assert_eq!(tag.length, Some(120));
+        assert_eq!(tag.track, Some(7));
+
+        assert!(parse_spc_header(b"not an spc").is_err());
+    }
+
+    #[test]
+    fn rating_str_rounds_and_clamps() {
+        assert_eq!(rating_str(4.5), "★★★★⯨");
+        assert_eq!(rating_str(2.2), "★★☆☆☆");
+        assert_eq!(rating_str(0.0), "☆☆☆☆☆");
+        assert_eq!(rating_str(7.0), "★★★★★");
+        assert_eq!(rating_str(-1.0), "☆☆☆☆☆");
+        assert_eq!(rating_str(f32::NAN).chars().count(), 5);
+    }
+
+    #[test]
+    fn peak_gain_reaches_target() {
+        assert!((peak_gain(0.5, -1.0) - 5.0206).abs() < 1e-3);
+        assert!(peak_gain(1.0, 0.0).abs() < 1e-9);
+        assert!((peak_gain(1.0, -3.0) + 3.0).abs() < 1e-9);
+        assert_eq!(peak_gain(0.0, -1.0), 0.0);
+    }
+
+    #[test]
+    fn parse_duration_forms() {
+        assert_eq!(parse_duration("95"), Ok(95.0));
+        assert_eq!(parse_duration(" 2.5 "), Ok(2.5));
+        assert_eq!(parse_duration("1:35"), Ok(95.0));
+        assert_eq!(parse_duration("0:07.5"), Ok(7.5));
+        assert!(parse_duration("1:60").is_err());
+        assert!(parse_duration("-3").is_err());
+        assert!(parse_duration("abc").is_err());
+        assert!(parse_duration("inf").is_err());
+    }
+
+    #[test]
+    fn smwc_id_forms() {
+        assert_eq!(smwc_id("12345"), Some("12345"));
+        assert_eq!(smwc_id(" 42 "), Some("42"));
+        assert_eq!(smwc_id("https://www.smwcentral.net/?p=section&a=details&id=31337"), Some("31337"));
+        assert_eq!(smwc_id("https://dl.smwcentral.net/download/777/"), Some("777"));
+        assert_eq!(smwc_id("https://dl.smwcentral.net/download/777"), Some("777"));
+        assert_eq!(smwc_id("forest theme"), None);
+        assert_eq!(smwc_id("https://www.smwcentral.net/?p=section&s=smwmusic"), None);
+    }
+
+    #[test]
+    fn spc_title_styles() {
+        let bare = Path::new("pack/03.spc");
+        assert_eq!(spc_title(bare, TrackTitleStyle::Raw), "03");
+        assert_eq!(spc_title(bare, TrackTitleStyle::Stripped), "3");
+        assert_eq!(spc_title(bare, TrackTitleStyle::Ordinal), "3rd");
+        assert_eq!(spc_track_number(bare), Some(3));
+
+        let named = Path::new("pack/03 - Forest_Theme.spc");
+        assert_eq!(spc_title(named, TrackTitleStyle::Raw), "03 - Forest Theme");
+        assert_eq!(spc_title(named, TrackTitleStyle::Stripped), "Forest Theme");
+        assert_eq!(spc_title(named, TrackTitleStyle::Ordinal), "Forest Theme");
+        assert_eq!(spc_track_number(named), Some(3));
+
+        assert_eq!(spc_title(Path::new("00.spc"), TrackTitleStyle::Stripped), "0");
+        assert_eq!(spc_title(Path::new("Overworld.spc"), TrackTitleStyle::Ordinal), "Overworld");
+        assert_eq!(spc_track_number(Path::new("Overworld.spc")), None);
+        assert_eq!(spc_track_number(Path::new("00.spc")), None);
+        assert_eq!(spc_track_number(Path::new("300.spc")), None);
+    }
+
+    #[test]
+    fn forced_album_modes() {
+        let dir = tempfile::tempdir().unwrap();
+        let single = vec![dir.path().join("05 - Castle.spc")];
+        let pack = vec![dir.path().join("Intro.spc"), dir.path().join("Outro.spc")];
+        for p in single.iter().chain(&pack) {
+            fs::write(p, b"not really an SPC").unwrap();
+        }
+
+        // Forced on, a lone SPC is numbered and titled like a pack track
+        let tags = s2w_track_tags(&single, None, true, TrackTitleStyle::Stripped);
+        let tag = tags[0].as_ref().unwrap();
+        assert_eq!((tag.track, tag.song.as_str()), (Some(5), "Castle"));
+        assert!(s2w_track_tags(&single, None, false, TrackTitleStyle::Stripped)[0].is_none());
+
+        let tags = s2w_track_tags(&pack, None, true, TrackTitleStyle::Stripped);
+        assert_eq!(tags.iter().map(|t| t.as_ref().unwrap().track).collect::<Vec<_>>(), [Some(1), Some(2)]);
+
+        // Forced off, a pack comes down to one track (the first, without a prompt)
+        assert_eq!(s2w_pick_track(&pack, false).unwrap(), pack[0]);
+        assert!(s2w_track_tags(&pack[..1], None, false, TrackTitleStyle::Stripped)[0].is_none());
+    }
+
+    #[test]
+    fn discs_from_subfolders() {
+        for keep_dirs in [false, true] {
+            let dir = tempfile::tempdir().unwrap();
+            let zip = dir.path().join("ost.zip");
+            fs::write(&zip, zip_of(&[
+                ("OST/Disc 2/01 Title.spc", b"x"), ("OST/Disc 10/01 Staff Roll.spc", b"x"),
+                ("OST/Disc 1/Overworld.spc", b"x"), ("OST/Disc 1/10 Castle.spc", b"x"), ("OST/Disc 1/2 Cave.spc", b"x"), ("OST/Disc 2/Map.spc", b"x"),
+            ])).unwrap();
+
+            let out = dir.path().join("out");
+            let (mut spc_files, _, sources) = s2w_extract(zip.to_str().unwrap(), &out, &[], keep_dirs, &RunLog::open(None, false).unwrap(), true, true).unwrap();
+            let discs = s2w_order_discs(&mut spc_files, &sources);
+            let tags = s2w_track_tags(&spc_files, discs.as_deref(), true, TrackTitleStyle::Stripped);
+
+            let got: Vec<(Option<u8>, Option<u8>, &str)> = tags.iter().map(|t| t.as_ref().unwrap()).map(|t| (t.disc, t.track, t.song.as_str())).collect();
+            assert_eq!(got, [
+                (Some(1), Some(2), "Cave"), (Some(1), Some(10), "Castle"), (Some(1), Some(3), "Overworld"),
+                (Some(2), Some(1), "Title"), (Some(2), Some(2), "Map"),
+                (Some(3), Some(1), "Staff Roll"),
+            ]);
+        }
+
+        // One folder (shared or not) is one disc, i.e. none
+        let sources = EntrySources::from([(PathBuf::from("a.spc"), PathBuf::from("Pack/a.spc")), (PathBuf::from("b.spc"), PathBuf::from("Pack/b.spc"))]);
+        assert_eq!(s2w_order_discs(&mut [PathBuf::from("b.spc"), PathBuf::from("a.spc")], &sources), None);
+    }
+
+    #[test]
+    fn natural_ordering() {
+        let mut names = vec!["Track 10", "track 2", "Track 1", "Track", "Track 02b", "Track 99999999999999999999999999999999999999999"];
+        names.sort_by_key(|n| natural_key(n));
+        assert_eq!(names, ["Track", "Track 1", "track 2", "Track 02b", "Track 10", "Track 99999999999999999999999999999999999999999"]);
+    }
+
+    #[test]
+    fn cache_entries_are_listed() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("12345.json"), file_json(serde_json::json!([])).to_string()).unwrap();
+        fs::write(dir.path().join("12345.zip"), zip_of(&[("a.spc", b"x")])).unwrap();
+        fs::write(dir.path().join("77.zip"), b"PK").unwrap();
+        fs::write(dir.path().join("8.json"), b"{ not metadata").unwrap();
+        fs::write(dir.path().join("notes.txt"), b"ignore me").unwrap();
+        fs::write(dir.path().join("9.tmp"), b"ignore me too").unwrap();
+
+        let entries = cache_entries(dir.path()).unwrap();
+        assert_eq!(entries.iter().map(|e| (e.id, e.name.as_deref(), e.metadata_bytes.is_some(), e.zip_bytes)).collect::<Vec<_>>(), [
+            (8, None, true, None),
+            (77, None, false, Some(2)),
+            (12345, Some("Forest Theme"), true, Some(fs::metadata(dir.path().join("12345.zip")).unwrap().len())),
+        ]);
+        assert!(entries.iter().all(|e| e.cached.is_some()));
+    }
+
+    /// A canonical 44-byte PCM WAV header (plus a LIST chunk before fmt, to be sure chunks are walked).
+    fn wav_header(bits: u16) -> Vec<u8> {
+        let mut wav = b"RIFF\0\0\0\0WAVELIST\x03\0\0\0abc\0fmt \x10\0\0\0\x01\0\x02\0".to_vec();
+        wav.extend(32000u32.to_le_bytes());
+        wav.extend((32000 * 2 * bits as u32 / 8).to_le_bytes());
+        wav.extend((2 * bits / 8).to_le_bytes());
+        wav.extend(bits.to_le_bytes());
+        wav.extend(b"data\0\0\0\0");
+        wav
+    }
+
+    fn flac_header(bits: u16) -> Vec<u8> {
+        let mut flac = b"fLaC\x80\x00\x00\x22".to_vec();
+        let mut info = [0u8; 34];
+        info[12] = ((bits - 1) >> 4) as u8 & 1;
+        info[13] = (((bits - 1) & 0xF) as u8) << 4;
+        flac.extend(info);
+        flac
+    }
+
+    #[test]
+    fn audio_bits_from_headers() {
+        let dir = tempfile::tempdir().unwrap();
+        let cases: [(&str, Vec<u8>, Option<u16>); 5] = [
+            ("a.wav", wav_header(24), Some(24)),
+            ("b.wav", wav_header(16), Some(16)),
+            ("c.flac", flac_header(24), Some(24)),
+            ("d.flac", flac_header(16), Some(16)),
+            ("e.mp3", b"ID3\x04".to_vec(), None),
+        ];
+        for (name, data, bits) in cases {
+            let loc = dir.path().join(name);
+            fs::write(&loc, data).unwrap();
+            assert_eq!(audio_bits(&loc), bits, "{}", name);
+        }
+        assert_eq!(audio_bits(&dir.path().join("missing.wav")), None);
+    }
+
+    #[test]
+    fn dither_only_when_reducing() {
+        // 24-bit --from-wav/--keep-ext source down to 16: dithered as asked
+        assert_eq!(sox_dither(Some(Dither::Tpdf), Some(24), Some(16)), (&[][..], &["dither"][..]));
+        assert_eq!(sox_dither(Some(Dither::Shibata), Some(24), Some(16)), (&[][..], &["dither", "-s"][..]));
+        assert_eq!(sox_dither(Some(Dither::None), Some(24), Some(16)), (&["-D"][..], &[][..]));
+        assert_eq!(ffmpeg_dither(Some(Dither::Tpdf), Some(24), Some(16)), Some("aresample=dither_method=triangular"));
+        assert_eq!(ffmpeg_dither(Some(Dither::Shibata), Some(24), Some(16)), Some("aresample=dither_method=shibata"));
+        assert_eq!(ffmpeg_dither(Some(Dither::None), Some(24), Some(16)), None);
+
+        // Same or more bits, no --bits, unknown source or no --dither: nothing added
+        for (src, bits) in [(Some(16), Some(16)), (Some(16), Some(24)), (Some(24), None), (None, Some(16))] {
+            assert_eq!(sox_dither(Some(Dither::Tpdf), src, bits), (&[][..], &[][..]));
+            assert_eq!(ffmpeg_dither(Some(Dither::Tpdf), src, bits), None);
+        }
+        assert_eq!(sox_dither(None, Some(24), Some(16)), (&[][..], &[][..]));
+        assert_eq!(ffmpeg_dither(None, Some(24), Some(16)), None);
+    }
+
+    #[test]
+    fn ordinals() {
+        let cases = [("1", "1st"), ("02", "2nd"), ("3", "3rd"), ("4", "4th"), ("11", "11th"), ("12", "12th"), ("13", "13th"), ("21", "21st"), ("112", "112th"), ("0", "0th"), ("100000000000000000000001", "100000000000000000000001st")];
+        for (digits, expected) in cases {
+            assert_eq!(ordinal(digits), expected);
+        }
+    }
+
+    #[test]
+    fn resource_forks_are_recognized() {
+        assert!(is_resource_fork(Path::new("__MACOSX/._song.spc")));
+        assert!(is_resource_fork(Path::new("__MACOSX/music/song.spc")));
+        assert!(is_resource_fork(Path::new("music/._song.spc")));
+        assert!(!is_resource_fork(Path::new("music/song.spc")));
+        assert!(!is_resource_fork(Path::new("music/_song.spc")));
+    }
+
+    #[test]
+    fn extraction_skips_resource_forks() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip = dir.path().join("music.zip");
+        fs::write(&zip, zip_of(&[("__MACOSX/._song.spc", b"AppleDouble"), ("._song.spc", b"AppleDouble"), ("song.spc", b"SNES-SPC700")])).unwrap();
+
+        let out = dir.path().join("out");
+        let (extracted, skipped, _) = s2w_extract(zip.to_str().unwrap(), &out, &[], false, &RunLog::open(None, false).unwrap(), true, true).unwrap();
+        assert_eq!(extracted, [out.join("song.spc")]);
+        assert!(skipped.is_empty());
+        assert_eq!(fs::read(out.join("song.spc")).unwrap(), b"SNES-SPC700");
+    }
+
+    #[test]
+    fn flat_name_avoids_clashes() {
+        let dest = Path::new("out");
+        assert_eq!(flat_name(dest, Path::new("a/song.spc"), &[]), dest.join("song.spc"));
+        assert_eq!(flat_name(dest, Path::new("b/song.spc"), &[dest.join("Song.spc")]), dest.join("song (b).spc"));
+        assert_eq!(flat_name(dest, Path::new("b/song.spc"), &[dest.join("song.spc"), dest.join("song (b).spc")]), dest.join("song (2).spc"));
+        assert_eq!(flat_name(dest, Path::new("song.nspc"), &[dest.join("song.spc")]), dest.join("song (2).nspc"));
+    }
+
+    #[test]
+    fn gunzip_lenient_inflates_json() {
+        let json = file_json(serde_json::json!("Alice")).to_string();
+        let mut gz = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        gz.write_all(json.as_bytes()).unwrap();
+        let body = gz.finish().unwrap();
+
+        let file: SMWCFile = serde_json::from_slice(&gunzip_lenient(&body)).unwrap();
+        assert_eq!(file.name, "Forest Theme");
+        assert_eq!(gunzip_lenient(json.as_bytes()), json.as_bytes());
+        assert_eq!(gunzip_lenient(&[0x1F, 0x8B, 0x00]), [0x1F, 0x8B, 0x00]);
+    }
+
+    #[test]
+    fn multiple_artists_round_trip_in_flac() {
+        let dir = tempfile::tempdir().unwrap();
+        let loc = dir.path().join("track.flac");
+        // Bare stream header: "fLaC" + a last-block STREAMINFO
+        let mut flac = b"fLaC\x80\x00\x00\x22".to_vec();
+        flac.resize(8 + 0x22, 0);
+        fs::write(&loc, flac).unwrap();
+
+        tag_artists(&loc, &["Alice".to_string(), "Bob".to_string()]).unwrap();
+        let tag = metaflac::Tag::read_from_path(&loc).unwrap();
+        assert_eq!(tag.get_vorbis("ARTIST").unwrap().collect::<Vec<_>>(), ["Alice", "Bob"]);
+    }
+
+    #[test]
+    fn stats_round_trip_in_mp3() {
+        use id3::TagLike;
+        let dir = tempfile::tempdir().unwrap();
+        let loc = dir.path().join("track.mp3");
+        fs::write(&loc, []).unwrap();
+        let mut blank = id3::Tag::new();
+        blank.set_title("placeholder");
+        blank.write_to_path(&loc, id3::Version::Id3v24).unwrap();
+
+        let file: SMWCFile = serde_json::from_value(file_json(serde_json::json!([{ "id": 1, "name": "Alice" }]))).unwrap();
+        s2w_tag(&loc, &file, &TagOpts { is_stats: true, ..Default::default() }, None, None).unwrap();
+
+        let tag = id3::Tag::read_from_path(&loc).unwrap();
+        let stats: HashMap<&str, &str> = tag.extended_texts().map(|t| (t.description.as_str(), t.value.as_str())).collect();
+        assert_eq!(stats.get("SMWC_DOWNLOADS"), Some(&"99"));
+        assert_eq!(stats.get("SMWC_RATING"), Some(&"4.50"));
+        assert_eq!(stats.get("SMWC_FEATURED"), Some(&"true"));
+        assert_eq!(tag.title(), Some("Forest Theme"));
+    }
+
+    #[test]
+    fn backoff_doubles_then_caps() {
+        let waits: Vec<f64> = (1..=8).map(|a| backoff_delay(a).as_secs_f64()).collect();
+        assert_eq!(waits, [0.5, 1.0, 2.0, 4.0, 8.0, 16.0, 30.0, 30.0]);
+        assert_eq!(backoff_delay(64), MAX_BACKOFF);
+        assert_eq!(backoff_delay(u32::MAX), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn sample_only_pack_is_explained() {
+        let skipped = vec![(PathBuf::from("samples/kick.brr"), None), (PathBuf::from("samples/SNARE.BRR"), None), (PathBuf::from("readme.txt"), Some(FileType::TXT))];
+        assert_eq!(no_spc_reason(&skipped), "This submission appears to be a sample pack (2 sample file(s)), not a playable SPC");
+    }
+
+    #[test]
+    fn summary_table_aligns_mixed_results() {
+        let report = |id: u16, name: &str, status: RunStatus, outputs: &[&str], error: Option<&str>| RunReport {
+            query: id.to_string(), id: Some(id), name: Some(name.to_string()), status,
+            outputs: outputs.iter().map(|o| o.to_string()).collect(), bytes: if outputs.is_empty() { 0 } else { 2048 }, error: error.map(str::to_string),
+        };
+        let reports = [
+            report(12345, "Forest Theme", RunStatus::Saved, &["Forest Theme.flac"], None),
+            report(7, "Pack", RunStatus::Saved, &["01.flac", "02.flac", "03.flac"], None),
+            report(404, "Gone", RunStatus::Failed, &[], Some(&"x".repeat(80))),
+        ];
+
+        let table = summary_table(&reports);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "ID     NAME          STATUS  OUTPUT                                                        SIZE");
+        assert_eq!(lines[2], "7      Pack          saved   01.flac (+2 more)                                             2.00 KiB");
+        assert!(lines[3].starts_with(&format!("404    Gone          failed  {}…", "x".repeat(59))));
+        for l in &lines {
+            assert_eq!(l.find("STATUS").or(l.find("saved")).or(l.find("failed")), Some(21));
+        }
+        assert_eq!(lines.iter().filter(|l| l.contains("saved")).count(), 2);
+    }
+
+    #[test]
+    fn extraction_keeps_extra_extensions() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip = dir.path().join("music.zip");
+        fs::write(&zip, zip_of(&[("song.spc", b"SNES-SPC700"), ("bonus.FLAC", b"fLaC"), ("cover.png", b"\x89PNG\r\n\x1a\n")])).unwrap();
+
+        let out = dir.path().join("out");
+        let (extracted, skipped, _) = s2w_extract(zip.to_str().unwrap(), &out, &["flac".to_string()], false, &RunLog::open(None, false).unwrap(), true, true).unwrap();
+        assert_eq!(extracted, [out.join("song.spc"), out.join("bonus.FLAC")]);
+        assert_eq!(skipped, [(PathBuf::from("cover.png"), Some(FileType::PNG))]);
+        assert_eq!(fs::read(out.join("bonus.FLAC")).unwrap(), b"fLaC");
+    }
+
+    #[test]
+    fn ascii_filenames_keep_unicode_tags() {
+        use id3::TagLike;
+        let dir = tempfile::tempdir().unwrap();
+        let loc = ascii_filename(&dir.path().join("Café Thème: Reprise?.wav"));
+        assert_eq!(loc, dir.path().join("Cafe Theme_ Reprise_.wav"));
+
+        let mut wav = wav_header(16);
+        let riff_len = (wav.len() - 8) as u32;
+        wav[4..8].copy_from_slice(&riff_len.to_le_bytes());
+        fs::write(&loc, wav).unwrap();
+        let mut file: SMWCFile = serde_json::from_value(file_json(serde_json::json!([{ "id": 1, "name": "Alice" }]))).unwrap();
+        file.name = "Café Thème".to_string();
+        s2w_tag(&loc, &file, &TagOpts::default(), None, None).unwrap();
+        assert_eq!(id3::Tag::read_from_path(&loc).unwrap().title(), Some("Café Thème"));
+    }
+
+    #[test]
+    fn each_style_maps_a_word() {
+        let styled = |style: Style| { let m = style.mapper(); "Song".chars().map(|c| *m.get(&c).unwrap_or(&c)).collect::<String>() };
+        assert_eq!(styled(Style::TinyCaps), "Sᴏɴɢ");
+        assert_eq!(styled(Style::Bold), "𝐒𝐨𝐧𝐠");
+        assert_eq!(styled(Style::Fullwidth), "Ｓｏｎｇ");
+        assert_eq!(styled(Style::Plain), "Song");
+    }
+
+    fn png_of(w: u32, h: u32) -> Vec<u8> {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend(w.to_be_bytes());
+        png.extend(h.to_be_bytes());
+        png.extend([8, 6, 0, 0, 0]);
+        png
+    }
+
+    #[test]
+    fn image_dims_reads_each_header() {
+        assert_eq!(image_dims(&png_of(640, 480)), Some((640, 480)));
+        assert_eq!(image_dims(b"GIF89a\x40\x01\xF0\x00"), Some((320, 240)));
+
+        let mut bmp = b"BM".to_vec();
+        bmp.resize(18, 0);
+        bmp.extend(256i32.to_le_bytes());
+        bmp.extend((-128i32).to_le_bytes()); // Top-down rows
+        assert_eq!(image_dims(&bmp), Some((256, 128)));
+
+        let mut jpeg = b"\xFF\xD8\xFF\xE0\x00\x10JFIF\x00\x01\x01\x00\x00\x01\x00\x01\x00\x00".to_vec();
+        jpeg.extend(b"\xFF\xC0\x00\x11\x08\x00\x64\x00\xC8");
+        assert_eq!(image_dims(&jpeg), Some((200, 100)));
+
+        assert_eq!(image_dims(&png_of(640, 480)[..20]), None);
+        assert_eq!(image_dims(b"not an image"), None);
+    }
+
+    #[test]
+    fn cover_score_prefers_big_and_square() {
+        assert!(cover_score((400, 400)) > cover_score((1000, 100)));
+        assert!(cover_score((400, 400)) > cover_score((300, 300)));
+        assert_eq!(cover_score((500, 500)), 250000.0);
+        assert_eq!(cover_score((0, 500)), 0.0);
+    }
+
+    /// Mock responses by path: the body, and whether it's sent chunked (no Content-Length).
+    type Routes = HashMap<String, (Vec<u8>, bool)>;
+
+    /// A throwaway HTTP server on localhost answering GETs with fixed bodies by path (query included), 404 otherwise.
+    struct MockServer {
+        root: String,
+        routes: std::sync::Arc<Mutex<Routes>>,
+        hits: std::sync::Arc<Mutex<HashMap<String, usize>>>, // Requests per path
+    }
+
+    impl MockServer {
+        fn start() -> MockServer {
+            use std::io::{BufRead, BufReader};
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let server = MockServer { root: format!("http://{}", listener.local_addr().unwrap()), routes: Default::default(), hits: Default::default() };
+
+            let (routes, hits) = (server.routes.clone(), server.hits.clone());
+            std::thread::spawn(move || for mut stream in listener.incoming().flatten() {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                reader.read_line(&mut request).unwrap();
+                let mut line = String::new();
+                while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                    line.clear();
+                }
+
+                let path = request.split_whitespace().nth(1).unwrap_or_default().to_string();
+                *hits.lock().unwrap().entry(path.clone()).or_insert(0) += 1;
+                let body = routes.lock().unwrap().get(&path).cloned();
+                let _ = match body {
+                    Some((body, true)) => stream.write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n")
+                        .and_then(|_| body.chunks(700).try_for_each(|c| {
+                            stream.write_all(format!("{:x}\r\n", c.len()).as_bytes())?;
+                            stream.write_all(c)?;
+                            stream.write_all(b"\r\n")
+                        }))
+                        .and_then(|_| stream.write_all(b"0\r\n\r\n")),
+                    Some((body, false)) => stream.write_all(format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len()).as_bytes())
+                        .and_then(|_| stream.write_all(&body)),
+                    None => stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"),
+                };
+            });
+
+            server
+        }
+
+        fn route(&self, path: &str, body: Vec<u8>) -> &MockServer {
+            self.routes.lock().unwrap().insert(path.to_string(), (body, false));
+            self
+        }
+
+        /// Like route, but sent with chunked transfer encoding, so without a Content-Length.
+        fn route_chunked(&self, path: &str, body: Vec<u8>) -> &MockServer {
+            self.routes.lock().unwrap().insert(path.to_string(), (body, true));
+            self
+        }
+
+        fn url(&self, path: &str) -> String {
+            format!("{}{}", self.root, path)
+        }
+
+        fn hits(&self, path: &str) -> usize {
+            self.hits.lock().unwrap().get(path).copied().unwrap_or(0)
+        }
+    }
+
+    #[test]
+    fn auto_cover_picks_the_best_image() {
+        let server = MockServer::start();
+        server.route("/small.png", png_of(100, 100)).route("/wide.png", png_of(1000, 100)).route("/big.png", png_of(400, 400));
+        let urls: Vec<String> = ["/small.png", "/wide.png", "/missing.png", "/big.png", "/small.png"].iter().map(|p| server.url(p)).collect();
+
+        let (data, mime) = s2w_auto_cover(&urls, &reqwest::blocking::Client::new(), 0, &RunLog::open(None, false).unwrap()).unwrap();
+        assert_eq!(data, png_of(400, 400));
+        assert_eq!(mime, MimeType::Png);
+        assert_eq!(server.hits("/small.png"), 1);
+        assert_eq!(server.hits("/missing.png"), 1);
+    }
+
+    #[test]
+    fn dedup_authors_keeps_first_of_each() {
+        let mut authors: Vec<SMWCUser> = ["Alice", "bob", "ALICE", "Carol", "Bob"].iter().enumerate()
+            .map(|(i, n)| SMWCUser { id: i as u16, name: n.to_string() })
+            .collect();
+        dedup_authors(&mut authors);
+        assert_eq!(authors.iter().map(|a| (a.id, a.name.as_str())).collect::<Vec<_>>(), [(0, "Alice"), (1, "bob"), (3, "Carol")]);
+    }
+
+    #[test]
+    fn ascii_ui_leaves_only_ascii() {
+        for (glyph, _) in ASCII_GLYPHS {
+            assert!(ascii_ui(&glyph.to_string()).is_ascii(), "{} stays non-ASCII", glyph);
+        }
+        let lines = [
+            format!("\x1B[38;2;255;196;0m⚠ Could not open {} in a file manager (…)\x1B[0m", "/tmp/Café"),
+            "Results for \"kirby\" (1–20 of 45):".to_string(),
+            format!("{} │ ≤ 0 dBFS → ✔ — ▶ ⏷", rating_str(3.5)),
+            summary_table(&[RunReport::panicked("12345", "ʙᴏᴏᴍ")]),
+        ];
+        for line in &lines {
+            let ascii = ascii_ui(line);
+            assert!(ascii.is_ascii(), "{:?} stays non-ASCII", ascii);
+        }
+        assert_eq!(ascii_ui("1–20 ✔"), "1-20 [OK]");
+    }
+
+    /// A WAV with a proper RIFF size around these sample bytes.
+    fn wav_of(samples: &[u8]) -> Vec<u8> {
+        let mut wav = wav_header(16);
+        let size_at = wav.len() - 4; // The data chunk's size, last in the header
+        wav[size_at..].copy_from_slice(&(samples.len() as u32).to_le_bytes());
+        wav.extend(samples);
+        let riff_len = (wav.len() - 8) as u32;
+        wav[4..8].copy_from_slice(&riff_len.to_le_bytes());
+        wav
+    }
+
+    /// Serves submission 12345 (file_json) from a mock SMWC API, its zip holding these entries, and routes this thread's API calls there.
+    fn mock_smwc(entries: &[(&str, &[u8])]) -> MockServer {
+        let server = MockServer::start();
+        let zip = zip_of(entries);
+        let mut json = file_json(serde_json::json!([{ "id": 1, "name": "Alice" }]));
+        json["download_url"] = server.url("/12345.zip").into();
+        json["size"] = zip.len().into();
+        server.route("/ajax.php?a=getfile&v=2&id=12345", json.to_string().into_bytes()).route("/12345.zip", zip);
+        API_ROOT.with(|r| *r.borrow_mut() = Some(server.root.clone()));
+        server
+    }
+
+    /// Unattended, quiet, uncached options keeping the rendered WAV, writing into out_dir.
+    fn run_opts(out_dir: &Path) -> RunOpts {
+        RunOpts {
+            output: None, output_dir: Some(out_dir.to_path_buf()), from_wav: None, spc2wav_outdir: None,
+            format: None, is_skip: true, is_format_valid: false, is_interactive: false, is_yes: true,
+            suppress_output: true, quiet: false, json: false, no_progress: true, no_delay: true, dry_run: false,
+            download_only: false, list_tracks: false, preview_description: false, no_convert: false, no_cache: true, force: false,
+            keep_dirs: false, ascii_filenames: false, open_folder: false, preserve_time: false, auto_cover: false, dedup_authors: false,
+            coverart: None, cover: None, style: Style::Plain, track_title_style: TrackTitleStyle::Stripped, album_mode: None,
+            require_tag: vec![], exclude_tag: vec![], keep_ext: vec![], trim_start: None, peak_normalize_to: None,
+            audio: AudioOpts::default(), tags: TagOpts::default(), jobs: 1, retries: 0,
+        }
+    }
+
+    /// Directory of shell-script stand-ins for spc2wav/sox/ffmpeg, installed as this test binary's tool paths:
+    /// spc2wav "renders" FAKE_RENDER beside the .spc (or into the directory named in <spc>.outdir), sox writes a bare FLAC for .flac (else copies), ffmpeg copies its input to its output (its arguments into <output>.args).
+    #[cfg(unix)]
+    fn fake_tools() -> &'static Path {
+        static DIR: OnceLock<PathBuf> = OnceLock::new();
+        DIR.get_or_init(|| {
+            use std::os::unix::fs::PermissionsExt;
+            let dir = tempfile::tempdir().unwrap().into_path();
+            fs::write(dir.join("render.wav"), wav_of(FAKE_RENDER)).unwrap();
+            fs::write(dir.join("render.flac"), flac_header(16)).unwrap();
+            let scripts = [
+                ("spc2wav", "d=$(dirname \"$1\"); [ -f \"$1.outdir\" ] && d=$(cat \"$1.outdir\"); cp \"$(dirname \"$0\")/render.wav\" \"$d/$(basename \"${1%.*}\").wav\""),
+                ("sox", "for a in \"$@\"; do case \"$a\" in *.wav) [ -z \"$src\" ] && src=$a;; *.*) dest=$a;; esac; done; [ \"${dest##*.}\" = flac ] && src=$(dirname \"$0\")/render.flac; cp \"$src\" \"$dest\""),
+                ("ffmpeg", "args=\"$*\"; while [ $# -gt 1 ]; do [ \"$1\" = -i ] && src=$2; shift; done; cp \"$src\" \"$1\"; echo \"$args\" > \"$1.args\""),
+            ];
+            for (name, script) in scripts {
+                fs::write(dir.join(name), format!("#!/bin/sh\n{}\n", script)).unwrap();
+                fs::set_permissions(dir.join(name), fs::Permissions::from_mode(0o755)).unwrap();
+            }
+
+            let tools: Vec<(&'static str, PathBuf)> = ["spc2wav", "sox", "ffmpeg"].iter().map(|t| (*t, dir.join(t))).collect();
+            let overrides: Vec<(&'static str, Option<&Path>)> = tools.iter().map(|(t, p)| (*t, Some(p.as_path()))).collect();
+            set_tool_paths(&overrides, &RunLog::open(None, false).unwrap()).unwrap();
+            dir
+        })
+    }
+
+    /// The sample bytes fake_tools' spc2wav renders every track as.
+    #[cfg(unix)]
+    const FAKE_RENDER: &[u8] = b"rendered";
+
+    #[cfg(unix)]
+    #[test]
+    fn prior_wav_is_reused_unless_forced() {
+        fake_tools();
+        let _server = mock_smwc(&[("forest.spc", b"SNES-SPC700")]);
+        let dir = tempfile::tempdir().unwrap();
+        let prior = dir.path().join("forest.wav");
+        fs::write(&prior, wav_of(b"prior run")).unwrap();
+        let (client, log) = (reqwest::blocking::Client::new(), RunLog::open(None, false).unwrap());
+        let contains = |hay: &[u8], needle: &[u8]| hay.windows(needle.len()).any(|w| w == needle);
+
+        let opts = RunOpts { no_convert: true, ..run_opts(dir.path()) };
+        let Ok(RunOutcome::Saved(res)) = s2w_run("12345", &opts, &client, &log) else { panic!("run failed") };
+        assert_eq!(res.outputs, [prior.to_string_lossy()]);
+        assert!(contains(&fs::read(&prior).unwrap(), b"prior run"));
+
+        let opts = RunOpts { force: true, ..opts };
+        s2w_run("12345", &opts, &client, &log).unwrap();
+        let out = fs::read(&prior).unwrap();
+        assert!(contains(&out, FAKE_RENDER) && !contains(&out, b"prior run"));
+    }
+
+    #[test]
+    fn cache_info_lists_both_files() {
+        let dir = Path::new("cache");
+        let entry = CacheEntry { id: 12345, name: Some("Forest Theme".to_string()), cached: None, metadata_bytes: Some(512), zip_bytes: None };
+        assert_eq!(cache_info(dir, &entry), format!("#12345 ▶  Forest Theme\n  cached    -\n  metadata  {} (512 B)\n  zip       -", dir.join("12345.json").display()));
+    }
+
+    #[test]
+    fn unidentified_files_report_their_hex_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.png"), png_of(1, 1)).unwrap();
+        fs::write(dir.path().join("b.bin"), (0x0Au8..0x20).collect::<Vec<u8>>()).unwrap();
+        fs::write(dir.path().join("c.bin"), [0xAB, 0xCD]).unwrap();
+
+        let found = identify(&[dir.path().to_path_buf()], true).unwrap();
+        assert_eq!(found.iter().map(|f| (f.variant.as_deref(), f.prefix.as_deref())).collect::<Vec<_>>(), [
+            (Some("PNG"), None),
+            (None, Some("0A 0B 0C 0D 0E 0F 10 11 12 13 14 15 16 17 18 19")),
+            (None, Some("AB CD")),
+        ]);
+        assert!(identify(&[dir.path().join("c.bin")], false).unwrap()[0].prefix.is_none());
+    }
+
+    #[test]
+    fn hex_query_is_not_an_id() {
+        assert_eq!(smwc_id("0x1F"), None);
+        assert_eq!(smwc_id("0x"), None);
+    }
+
+    #[test]
+    fn blank_names_get_a_placeholder() {
+        let mut file: SMWCFile = serde_json::from_value(file_json(serde_json::json!([]))).unwrap();
+        assert_eq!(placeholder_name(&file), None);
+        for blank in ["", "   ", "\t\n"] {
+            file.name = blank.to_string();
+            assert_eq!(placeholder_name(&file).as_deref(), Some("(untitled #12345)"));
+        }
+    }
+
+    #[test]
+    fn schemas_parse_and_describe_the_json_output() {
+        let schemas: Value = serde_json::from_str(&serde_json::to_string_pretty(&json_schemas()).unwrap()).unwrap();
+        let props = |ty: &str| schemas[ty]["properties"].as_object().unwrap_or_else(|| panic!("{} has no properties", ty)).keys().cloned().collect::<HashSet<String>>();
+
+        for ty in ["SMWCFile", "SPCHeader", "Identified", "RunResult", "DryRunPlan", "RunReport", "CacheEntry"] {
+            assert_eq!(schemas[ty]["title"], ty);
+        }
+        assert!(props("RunReport").is_superset(&["query", "id", "name", "status", "outputs", "bytes", "error"].map(String::from).into()));
+        assert!(props("RunResult").is_superset(&["outputs", "file"].map(String::from).into()));
+        assert!(props("SMWCFile").is_superset(&["id", "name", "authors", "download_url"].map(String::from).into()));
+        assert!(props("CacheEntry").contains("zip_bytes"));
+    }
+
+    #[test]
+    fn output_extension_picks_the_format() {
+        assert_eq!(output_format(Path::new("out/foo.mp3")).unwrap(), "mp3");
+        assert_eq!(output_format(Path::new("foo.FLAC")).unwrap(), "flac");
+        assert_eq!(output_format(Path::new("foo.wav")).unwrap(), "skip");
+        assert!(output_format(Path::new("foo.txt")).is_err());
+        assert!(output_format(Path::new("foo")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn output_path_gets_the_converted_track() {
+        fake_tools();
+        let _server = mock_smwc(&[("forest.spc", b"SNES-SPC700")]);
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("renamed").join("foo.mp3");
+        fs::create_dir_all(out.parent().unwrap()).unwrap();
+
+        let opts = RunOpts { output: Some(out.clone()), format: Some(output_format(&out).unwrap()), is_skip: false, is_format_valid: true, ..run_opts(dir.path()) };
+        let Ok(RunOutcome::Saved(res)) = s2w_run("12345", &opts, &reqwest::blocking::Client::new(), &RunLog::open(None, false).unwrap()) else { panic!("run failed") };
+        assert_eq!(res.outputs, [out.to_string_lossy()]);
+        assert!(out.is_file());
+        assert!(!dir.path().join("forest.wav").exists() && !dir.path().join("forest.mp3").exists());
+    }
+
+    #[test]
+    fn reveal_opens_the_resolved_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let opened = |loc: &Path| {
+            let mut seen = None;
+            reveal_with(loc, |d| { seen = Some(d.to_path_buf()); Ok::<(), Error>(()) });
+            seen.unwrap()
+        };
+
+        fs::create_dir(dir.path().join("a")).unwrap();
+        let nested = dir.path().join("a").join("..").join("track.flac");
+        assert_eq!(opened(&nested), fs::canonicalize(dir.path()).unwrap());
+        assert_eq!(opened(Path::new("track.flac")), env::current_dir().unwrap());
+    }
+
+    #[test]
+    fn preserve_time_stamps_the_submission_time() {
+        let _server = mock_smwc(&[("forest.spc", b"SNES-SPC700")]);
+        let dir = tempfile::tempdir().unwrap();
+
+        let opts = RunOpts { download_only: true, preserve_time: true, ..run_opts(dir.path()) };
+        s2w_run("12345", &opts, &reqwest::blocking::Client::new(), &RunLog::open(None, false).unwrap()).unwrap();
+        let mtime = FileTime::from_last_modification_time(&fs::metadata(dir.path().join("forest.spc")).unwrap());
+        assert_eq!(mtime.unix_seconds(), 1700000000);
+    }
+
+    #[test]
+    fn download_only_leaves_just_the_spcs() {
+        let server = mock_smwc(&[("music/forest.spc", b"SNES-SPC700"), ("music/cave.spc", b"SNES-SPC700"), ("readme.txt", b"hi")]);
+        let dir = tempfile::tempdir().unwrap();
+
+        let opts = RunOpts { download_only: true, ..run_opts(dir.path()) };
+        let Ok(RunOutcome::Saved(res)) = s2w_run("12345", &opts, &reqwest::blocking::Client::new(), &RunLog::open(None, false).unwrap()) else { panic!("run failed") };
+        let mut left: Vec<String> = fs::read_dir(dir.path()).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().to_string()).collect();
+        left.sort();
+        assert_eq!(left, ["cave.spc", "forest.spc"]);
+        assert_eq!(res.outputs.len(), 2);
+        assert_eq!(server.hits("/12345.zip"), 1);
+    }
+
+    #[test]
+    fn no_progress_hides_every_bar() {
+        assert!(s2w_bar(100, true).is_hidden());
+        let spinner = s2w_spinner("Fetching metadata…", true);
+        assert!(spinner.is_hidden());
+        spinner.finish_and_clear();
+
+        // The hidden bar still counts (for the byte totals) without drawing anything
+        let bar = s2w_bar(3, true);
+        bar.inc(2);
+        assert_eq!(bar.position(), 2);
+        assert!(bar.is_hidden());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn from_wav_converts_and_tags_without_downloading() {
+        fake_tools();
+        let server = mock_smwc(&[("forest.spc", b"SNES-SPC700")]);
+        let dir = tempfile::tempdir().unwrap();
+        let wav = dir.path().join("kept.wav");
+        fs::write(&wav, wav_of(b"earlier render")).unwrap();
+
+        let opts = RunOpts { from_wav: Some(wav.clone()), format: Some("flac".to_string()), is_skip: false, is_format_valid: true, ..run_opts(dir.path()) };
+        let Ok(RunOutcome::Saved(res)) = s2w_run("12345", &opts, &reqwest::blocking::Client::new(), &RunLog::open(None, false).unwrap()) else { panic!("run failed") };
+        let flac = dir.path().join("kept.flac");
+        assert_eq!(res.outputs, [flac.to_string_lossy()]);
+        assert_eq!(magictype_from_path(&flac).unwrap(), Some(FileType::FLAC));
+        let tag = metaflac::Tag::read_from_path(&flac).unwrap();
+        assert_eq!(tag.get_vorbis("TITLE").unwrap().collect::<Vec<_>>(), ["Forest Theme"]);
+        assert_eq!(tag.get_vorbis("ARTIST").unwrap().collect::<Vec<_>>(), ["Alice"]);
+
+        assert!(wav.is_file());
+        assert_eq!(server.hits("/12345.zip"), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn spc2wav_output_is_found_in_the_outdir_hint() {
+        fake_tools();
+        let (dir, elsewhere) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let log = RunLog::open(None, false).unwrap();
+        let spc = |name: &str| {
+            let spc = dir.path().join(name);
+            fs::write(&spc, b"SNES-SPC700").unwrap();
+            fs::write(spc.with_extension("spc.outdir"), elsewhere.path().to_str().unwrap()).unwrap();
+            spc
+        };
+
+        let wav = s2w_spc2wav(&spc("forest.spc"), Some(elsewhere.path()), &log).unwrap();
+        assert_eq!(wav, elsewhere.path().join("forest.wav"));
+        assert!(!dir.path().join("forest.spc").exists());
+
+        let err = s2w_spc2wav(&spc("cave.spc"), None, &log).unwrap_err();
+        assert!(format!("{:#}", err).contains("try --spc2wav-outdir"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_file_falls_back_to_ffmpeg_tagging() {
+        fake_tools();
+        let dir = tempfile::tempdir().unwrap();
+        let loc = dir.path().join("track.mp3");
+        fs::write(&loc, b"not an mpeg stream").unwrap();
+        let mut json = file_json(serde_json::json!([{ "id": 1, "name": "Alice" }]));
+        json["raw_fields"]["source"] = "n/a".into();
+        let file: SMWCFile = serde_json::from_value(json).unwrap();
+
+        s2w_tag(&loc, &file, &TagOpts::default(), None, None).unwrap();
+        assert_eq!(fs::read(&loc).unwrap(), b"not an mpeg stream");
+        let args = fs::read_to_string(dir.path().join(".tagging.track.mp3.args")).unwrap();
+        assert!(args.contains("-metadata title=Forest Theme") && args.contains("-metadata artist=Alice"));
+    }
+
+    #[test]
+    fn untaggable_file_keeps_its_audio_and_names_the_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let loc = dir.path().join("track.wav");
+        fs::write(&loc, b"RIFF\xFF\xFF\xFF\xFFWAVEbroken").unwrap();
+        let file: SMWCFile = serde_json::from_value(file_json(serde_json::json!([]))).unwrap();
+
+        let err = s2w_tag(&loc, &file, &TagOpts::default(), None, None).unwrap_err();
+        assert!(format!("{:#}", err).starts_with(&format!("could not tag {}", loc.display())));
+        assert_eq!(fs::read(&loc).unwrap(), b"RIFF\xFF\xFF\xFF\xFFWAVEbroken");
+    }
+
+    /// The arguments a Command was built with, as strings.
+    fn args_of(cmd: &Command) -> Vec<String> {
+        cmd.get_args().map(|a| a.to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn sox_trims_from_the_detected_start() {
+        let opts = AudioOpts { duration: Some(30.0), fade: Some(5.0), ..AudioOpts::default() };
+        let args = args_of(&sox_command(Path::new("in.wav"), Path::new("out.flac"), opts, Some(2.5), None));
+        assert_eq!(args, ["in.wav", "out.flac", "trim", "2.500", "30.000", "fade", "0", "30.000", "5.000"]);
+
+        let args = args_of(&sox_command(Path::new("in.wav"), Path::new("out.flac"), AudioOpts::default(), Some(0.25), Some(-1.5)));
+        assert_eq!(args, ["in.wav", "out.flac", "trim", "0.250", "gain", "-1.500"]);
+        assert_eq!(args_of(&sox_command(Path::new("in.wav"), Path::new("out.flac"), AudioOpts::default(), None, None)), ["in.wav", "out.flac"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn tool_path_overrides_reach_the_spawned_command() {
+        let dir = fake_tools();
+        let cmd = sox_command(Path::new("in.wav"), Path::new("out.flac"), AudioOpts::default(), None, None);
+        assert_eq!(cmd.get_program(), dir.join("sox"));
+        assert_eq!(tool("flac"), PathBuf::from("flac"));
+
+        let not_executable = tempfile::NamedTempFile::new().unwrap();
+        let err = set_tool_paths(&[("sox", Some(not_executable.path()))], &RunLog::open(None, false).unwrap()).unwrap_err();
+        assert!(err.to_string().ends_with("is not an executable file!"));
+        assert_eq!(tool("sox"), dir.join("sox"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn concurrent_runs_share_an_output_dir_cleanly() {
+        fake_tools();
+        let dir = tempfile::tempdir().unwrap();
+        // Same id on both sides, so a shared {id}.zip or .wav intermediate would collide
+        let runs: Vec<_> = ["forest", "cave"].map(|track| {
+            let out = dir.path().to_path_buf();
+            std::thread::spawn(move || {
+                let _server = mock_smwc(&[(&format!("{}.spc", track), b"SNES-SPC700")]);
+                let opts = RunOpts { format: Some("flac".to_string()), is_skip: false, is_format_valid: true, ..run_opts(&out) };
+                match s2w_run("12345", &opts, &reqwest::blocking::Client::new(), &RunLog::open(None, false).unwrap()) {
+                    Ok(RunOutcome::Saved(res)) => res.outputs,
+                    _ => panic!("{} run failed", track),
+                }
+            })
+        }).into_iter().collect();
+        let outputs: Vec<Vec<String>> = runs.into_iter().map(|r| r.join().unwrap()).collect();
+
+        assert_eq!(outputs, [[dir.path().join("forest.flac").to_string_lossy()], [dir.path().join("cave.flac").to_string_lossy()]]);
+        for flac in outputs.iter().flatten() {
+            assert_eq!(magictype_from_path(Path::new(flac)).unwrap(), Some(FileType::FLAC));
+        }
+        let mut left: Vec<String> = fs::read_dir(dir.path()).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().to_string()).collect();
+        left.sort();
+        assert_eq!(left, ["cave.flac", "forest.flac"]);
+    }
+
+    #[test]
+    fn preview_description_is_the_cleaned_description() {
+        let mut json = file_json(serde_json::json!([]));
+        json["raw_fields"]["description"] = format!("<b>Usage:</b> insert with AddmusicK &amp; enjoy!<br />\r\n\r\n\r\n{}", "loop ".repeat(25)).into();
+        let file: SMWCFile = serde_json::from_value(json).unwrap();
+        let expected = format!("Usage: insert with AddmusicK & enjoy!\n\n{}\n{}\n", "loop ".repeat(20).trim_end(), "loop ".repeat(5).trim_end());
+        assert_eq!(s2w_description(&file), expected);
+
+        let server = mock_smwc(&[("forest.spc", b"SNES-SPC700")]);
+        let dir = tempfile::tempdir().unwrap();
+        let opts = RunOpts { preview_description: true, ..run_opts(dir.path()) };
+        let outcome = s2w_run("12345", &opts, &reqwest::blocking::Client::new(), &RunLog::open(None, false).unwrap()).unwrap();
+        assert!(matches!(outcome, RunOutcome::Shown));
+        assert_eq!(server.hits("/12345.zip"), 0);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn chunked_download_is_counted_in_full() {
+        let server = MockServer::start();
+        let body: Vec<u8> = (0..5000u32).map(|i| i as u8).collect();
+        server.route_chunked("/12345.zip", body.clone());
+        let client = reqwest::blocking::Client::new();
+        assert_eq!(client.get(server.url("/12345.zip")).send().unwrap().content_length(), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("12345.zip");
+        let n = s2w_download(&server.url("/12345.zip"), dest.to_str().unwrap(), &client, 0, &RunLog::open(None, false).unwrap(), true, true).unwrap();
+        assert_eq!(n, 5000);
+        assert_eq!(fs::read(&dest).unwrap(), body);
+    }
+
+    #[test]
+    fn batch_reports_a_panic_and_carries_on() {
+        let queries: Vec<String> = ["1", "2", "3"].map(String::from).into();
+        let ran = Mutex::new(vec![]);
+        let reports = s2w_batch(&queries, &run_opts(Path::new(".")), false, &RunLog::open(None, false).unwrap(), |query| {
+            ran.lock().unwrap().push(query.to_string());
+            match query {
+                "2" => panic!("deep in a dependency"),
+                "3" => bail!("no such submission"),
+                _ => Ok(RunOutcome::Shown),
+            }
+        });
+
+        assert_eq!(*ran.lock().unwrap(), ["1", "2", "3"]);
+        let statuses: Vec<RunStatus> = reports.iter().map(|r| r.status).collect();
+        assert_eq!(statuses, [RunStatus::Shown, RunStatus::Panicked, RunStatus::Failed]);
+        assert_eq!(reports[1].error.as_deref(), Some("deep in a dependency"));
+        assert_eq!(reports[1].id, Some(2));
+        assert_eq!(reports[2].error.as_deref(), Some("no such submission"));
+    }
+}
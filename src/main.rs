@@ -1,368 +1,320 @@
-use chrono::prelude::DateTime;
-use chrono::Local;
-use chrono::{Datelike, Timelike, Utc};
-use clap::Parser;
-use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
-use regex::Regex;
-use reqwest::Url;
-use serde::Deserialize;
-use serde_json::Value;
-use which::which;
-use audiotags::{Album, MimeType, Picture, Tag};
-use inquire::{Confirm, Select};
-use strum::IntoEnumIterator;
-use strum_macros::{AsRefStr, EnumIter};
-
-use std::collections::HashMap;
-use std::io::{ErrorKind, Write};
-use std::os::unix::fs::MetadataExt;
-use std::process::Command;
-use std::thread::sleep;
-use std::time::{Duration, UNIX_EPOCH};
-use std::{fs, io};
-use std::io::Error;
-use regex_macro::regex;
-
-const TINY_CAPS_MAPPING: [char; 26] = ['ᴀ', 'ʙ', 'ᴄ', 'ᴅ', 'ᴇ', 'ғ', 'ɢ', 'ʜ', 'ɪ', 'ᴊ', 'ᴋ', 'ʟ', 'ᴍ', 'ɴ', 'ᴏ', 'ᴘ', 'ꞯ', 'ʀ', 's', 'ᴛ', 'ᴜ', 'ᴠ', 'ᴡ', 'x', 'ʏ', 'ᴢ'];
-
-// Based on https://en.wikipedia.org/wiki/Magic_number_(programming)
-#[derive(AsRefStr, EnumIter, PartialEq)]
-enum FileType {
-    JPEG,
-    GIF,
-    PNG,
-    VTF,
-    MIDI,
-    UnixScript,
-    ELF,
-    PDF,
-    MBR,
-    TIFF,
-    WAD,
-    ZIP,
-    TAR,
-    XML,
-    TXT,
-    HEIC,
-    WEBP,
-    NES,
-    BMP,
-    SPC,
-    WAV,
-    AVI,
-    AIFF,
-    MP3,
-    MP4,
-    OGG,
-    FLAC,
-    M4A,
-    AAC
-}
-
-impl FileType {
-    // Returned as a regex pattern with byte values delimited with ; for the sake of generic sizing.
-    // e.g. [4A BC] or [A3 BB 9F] -> (4A;BC|A3;BB;9F)
-    fn magic(&self) -> &str {
-        match *self {
-            FileType::JPEG => "(FF;D8;FF;DB|FF;D8;FF;E0;00;10;4A;46;49;46;00;01|FF;D8;FF;EE|FF;D8;FF;E1;([0-9A-F]{2};){2}45;78;69;66;00;00|FF;D8;FF;E0)", // TODO: this excludes JPEG2000, needed?
-            FileType::GIF => "(47;49;46;38;39;61|47;49;46;38;37;61).*", // GIF89a or GIF87a
-            FileType::PNG => "(89;50;4E;47;0D;0A;1A;0A).*", // \211PNG\r\n\032\n
-            FileType::VTF => "(00;46;54;56).*", // VTF\0 (https://developer.valvesoftware.com/wiki/VTF_(Valve_Texture_Format))
-            FileType::MIDI => "(4D;54;68;64).*", // MThd
-            FileType::UnixScript => "(23;21).*", // #!
-            FileType::ELF => "(7F;45;4C;46).*", // 0x7F + ELF
-            FileType::PDF => "(25;50;44;46;2D).*", // %PDF-
-            FileType::MBR => ".*(55;AA)", // 0x55AA
-            FileType::TIFF => "(49;49;2A;00|4D;4D;00;2A|49;49;2B;00|4D;4D;00;2B).*", // II (le) or MM (be) + 0x42
-            FileType::WAD => "(49;57;41;44|50;57;41;44|57;41;44;32|57;41;44;33).*", // IWAD/PWAD (Doom), WAD2 (Quake), WAD3 (Half-Life)
-            FileType::ZIP => "(50;4B;03:04).*", // PK♥♦
-            FileType::TAR => "(75;73;74;61;72;00;30;30|75;73;74;61;72;20;20;00).*", // ustar␀00 or ustar␠␠␀
-            FileType::XML => "(3C;3F;78;6D;6C;20|3C;00;3F;00;78;00;6D;00;6C;00;20|00;3C;00;3F;00;78;00;6D;00;6C;00;20|3C;00;00;00;3F;00;00;00;78;00;00;00;6D;00;00;00;6C;00;00;00;20;00;00;00|00;00;00;3C;00;00;00;3F;00;00;00;78;00;00;00;6D;00;00;00;6C;00;00;00;20).*",
-            FileType::TXT => "(EF;BB;BF|FF;FE|FE;FF|FF;FE;00;00|00;00;FE;FF).*", // ï»¿, ÿþ, þÿ, ÿþ␀␀, or ␀␀þÿ
-            FileType::HEIC => "(66;74;79;70;68;65;69;63;66;74;79;70;6D", // ftypheic
-            FileType::WEBP => "(52;49;46;46;([0-9A-F]{2};){4}57;45;42;50).*", // RIFF????WEBP
-            FileType::NES => "(4E;45;53;1A).*", // NES␚
-            FileType::BMP => "(42;4D).*", // BM
-            FileType::SPC => "(53;4E;45;53;2D;53;50;43;37;30;30;20;53;6F;75;6E;64;20;46;69;6C;65;20;44;61;74;61;20;76;30;2E;33;30;1A;1A).*", // SNES-SPC700 Sound File Data v0.30 + 2x 0x26
-            FileType::WAV => "(52;49;46;46;([0-9A-F]{2};){4}57;41;56;45).*", // RIFF????WAVE
-            FileType::AVI => "(52;49;46;46;([0-9A-F]{2};){4}41;56;49;20).*", // RIFF????AVI␠
-            FileType::AIFF => "(46;4F;52;4D;([0-9A-F]{2};){4}41;49;46;46).*", // FORM????AIFF
-            FileType::MP3 => "(FF;FB|FF;F3|FF;F2|49;44;33).*", // ÿû, ÿó, or ÿò (or ID3)
-            FileType::MP4 => "(66;74;79;70;4D;53;4E;56).*", // ftypMSNV
-            FileType::OGG => "(4F;67;67;53).*", // OggS
-            FileType::FLAC => "(66;4C;61;43).*", // fLaC
-            FileType::M4A => "(00;00;00;(1C|20);66;74;79;70;4D;34;41;20).*", // 0x000000 ftypM4A. There was a single byte difference for some reason? Also could also be M4A_? (https://docs.fileformat.com/audio/m4a/)
-            FileType::AAC => "(FF;F1|FF;F9).*", // ÿñ or ÿù
-        }
-    }
-
-    /// Utility method for converting to audiotags::MimeType
-    fn mime(&self) -> Result<MimeType, Error> {
-        match *self {
-            FileType::JPEG => Ok(MimeType::Jpeg),
-            FileType::PNG => Ok(MimeType::Png),
-            FileType::TIFF => Ok(MimeType::Tiff),
-            FileType::BMP => Ok(MimeType::Bmp),
-            FileType::GIF => Ok(MimeType::Gif),
-            _ => Err(Error::from(ErrorKind::Unsupported)) // TODO: wrong way to indicate unsupported datatype?
-        }
-    }
-}
+use clap::{CommandFactory, Parser, Subcommand};
+use anyhow::{bail, Context};
 
-/// Macro for lazy-checking if string matches pattern.
-macro_rules! is_regex {
-    ($str:expr, $pat:expr) => {{
-        use regex::Regex;
-        let re = regex!($pat).unwrap();
-        re.is_match($str)
-    }}
-}
+use std::io::IsTerminal;
+use std::{env, fs, io};
+use std::path::PathBuf;
+use smwc2wav::*;
 
-#[derive(Parser)]
-#[command(version, about, long_about = None)]
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
     // #[command(flatten)]
     // verbose: clap_verbosity_flag::Verbosity,
 
-    // Query (SMWCentral ID or URL)
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// Query (SMWCentral ID or URL); prompted for interactively when omitted
     #[arg(short, long)]
-    query: String,
+    query: Option<String>,
+
+    /// Search the Music section by name and pick from the results instead of giving an ID
+    #[arg(short, long, conflicts_with_all = ["query", "file"])]
+    search: Option<String>,
+
+    /// Show at most this many search results per screen (default: SMWC's own page size)
+    #[arg(long, requires = "search", conflicts_with_all = ["query", "file"], value_parser = clap::value_parser!(u16).range(1..))]
+    limit: Option<u16>,
+
+    /// Start search results from this listing page
+    #[arg(long, requires = "search", conflicts_with_all = ["query", "file"], value_parser = clap::value_parser!(u32).range(1..))]
+    page: Option<u32>,
+
+    /// Batch: a text file of queries, one per line (blank lines skipped); -f is already --format
+    #[arg(short = 'F', long, conflicts_with_all = ["query", "output", "from_wav"])]
+    file: Option<PathBuf>,
 
+    /// Album tag; defaults to the submission name
     #[arg(short, long, default_missing_value = None)]
     album: Option<String>,
 
+    /// Genre tag; defaults to "Game"
+    #[arg(long)]
+    genre: Option<String>,
+
+    /// Year tag (e.g. the source game's release); defaults to the year the submission was uploaded
+    #[arg(long, value_parser = clap::value_parser!(i32).range(1900..=9999))]
+    year: Option<i32>,
+
+    /// Cover art image file (jpg/png/gif/webp) to embed in the output
     #[arg(short, long, default_missing_value = None)]
     coverart: Option<String>,
 
     #[arg(short, long, default_missing_value = None)]
     immediate: Option<bool>,
 
-    #[arg(short, long, default_missing_value = None, default_value = Some("skip".into()))]
+    /// Download without the "Confirm download..." pause (implied when stdin isn't a terminal or in batch mode)
+    #[arg(short, long)]
+    yes: bool,
+
+    /// Write each author as a separate artist value (mp3/flac/m4a) instead of one joined string
+    #[arg(long)]
+    multi_artist: bool,
+
+    /// Drop repeated author names (case-insensitive, first one kept) from the artist tag + banner
+    #[arg(long)]
+    dedup_authors: bool,
+
+    /// Also write the submission's download count, rating and featured status as custom tags
+    #[arg(long)]
+    embed_stats: bool,
+
+    /// Without -c, use the best (largest, squarest) of the submission's own images as cover art
+    #[arg(long, conflicts_with = "coverart")]
+    auto_cover: bool,
+
+    /// Format to convert the rendered WAV to (flac/mp3/aiff/ogg, or m4a/aac/opus via ffmpeg; "skip" keeps the WAV)
+    #[arg(short, long, default_missing_value = None)]
     format: Option<String>,
-}
 
-#[derive(Deserialize, Debug)]
-struct SMWCUser {
-    id: u16,
-    name: String
-}
+    /// Exact output path (format inferred from extension, overriding --format)
+    #[arg(short, long, default_missing_value = None)]
+    output: Option<PathBuf>,
 
-#[derive(Deserialize, Debug)]
-struct SMWCAudioFields {
-    size: String,
-    #[serde(rename = "type")]
-    variant: String,
-    samples: String,
-    source: String,
-    duration: String,
-    featured: bool,
-    description: String
-}
+    /// Directory to write finished files (and the run's scratch files) into; created if missing
+    #[arg(long, conflicts_with = "output")]
+    output_dir: Option<PathBuf>,
 
-#[derive(Debug, Deserialize)]
-struct SMWCFile {
-    id: u16,
-    section: String,
-    name: String,
-    time: u64,
-    authors: Vec<SMWCUser>,
-    submitter: Option<SMWCUser>,
-    tags: Vec<String>,
-    images: Option<Vec<String>>,
-    rating: Option<f32>,
-    size: u32,
-    downloads: u16,
-    download_url: String,
-    obsoleted_by: Option<u16>,
-    // fields: SMWCAudioFields, <- unnecessary
-    raw_fields: SMWCAudioFields
-}
+    /// Only take submissions carrying every one of these SMWC tags (case-insensitive; repeatable or comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    require_tag: Vec<String>,
 
+    /// Skip submissions carrying any of these SMWC tags
+    #[arg(long, value_delimiter = ',')]
+    exclude_tag: Vec<String>,
 
-/// Serde JSON deserialiser to capture anything as a string (* -> String)
-fn de_unistr<'de, D>(deserializer: D) -> Result<String, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    // Deserialize into blanket serde_json::Value then case-match
-    let value = Value::deserialize(deserializer)?;
-
-    Ok(match value {
-        Value::Null => "null".to_string(),
-        Value::Bool(b) => b.to_string(),
-        Value::Number(n) => n.to_string(),
-        Value::String(s) => s,
-        Value::Array(arr) => format!("{:?}", arr),
-        Value::Object(o) => format!("{:?}", o),
-    })
-}
+    /// Also keep these (non-SPC audio) extensions from the archive, e.g. --keep-ext flac,mp3
+    #[arg(long, value_delimiter = ',', default_missing_value = None)]
+    keep_ext: Vec<String>,
 
+    /// Cut this many seconds off the start of the track (e.g. a silent lead-in) when converting
+    #[arg(long)]
+    trim_start: Option<f64>,
 
-/// Parses Unix time to human-readable time object + timezone difference.
-fn unix_to_hrtime(unix_off: u64) -> DateTime<Utc> {
-    let tz_off = Local::now().offset().local_minus_utc();
-    let unix_time = UNIX_EPOCH + Duration::from_secs((unix_off as i64 + tz_off as i64) as u64);
-    DateTime::<Utc>::from(unix_time)
-}
+    /// Peak-normalize converted audio to this level in dBFS (≤ 0, e.g. -1)
+    #[arg(long, allow_negative_numbers = true)]
+    peak_normalize_to: Option<f64>,
 
-/// Produces "quantity" string via filling with provided active/inactive chars.
-fn q_str(active_c: &char, inactive_c: &char, active_q: u8, total_q: u8) -> String {
-    let mut res = String::with_capacity(total_q as usize);
-    res.push_str(&*active_c.to_string().repeat(active_q as usize));
-    res.push_str(&*inactive_c.to_string().repeat((total_q - active_q) as usize));
-    res
-}
+    /// Resample converted audio to this rate in Hz (SPC rips are 32000; 44100 plays anywhere)
+    #[arg(long)]
+    rate: Option<u32>,
 
-/// Strips "HTML" (html string) to its string equivalent.
-/// Tentatively <br>\r\n → \n and condenses consecutive \n into one; unless SMWc's "safe HTML" has other HTML-specific symbols.
-fn strip_html(h_str: &str) -> String {
-    let res = h_str.replace("<br>\\r\\n", "\n");
-    let rgx = Regex::new("r(\\n)+").unwrap();
-    rgx.replace_all(&*res, "\n").into()
-}
+    /// Bit depth of converted lossless audio (16 or 24; flac/aiff only)
+    #[arg(long)]
+    bits: Option<u8>,
 
-/// Converts alphabetical vector of mappings to map.
-fn alphavec_to_map(alphavec: [char; 26]) -> HashMap<char, char> {
-    ('a'..='z').zip(alphavec.iter().copied()).collect()
-}
+    /// Dither to use when --bits is below the source's depth (a 24-bit --from-wav or --keep-ext file); the encoder decides otherwise
+    #[arg(long, value_enum, requires = "bits")]
+    dither: Option<Dither>,
 
-/// Clamps string to specified max length.
-fn strclamp(str: &str, max_len: usize) -> String {
-    let clamped_lines = str.len() / max_len;
-    let mut res = String::with_capacity(str.len() + clamped_lines);
+    /// Channel count of converted audio (1 downmixes to mono, 2 keeps stereo)
+    #[arg(long)]
+    channels: Option<u8>,
 
-    for i in 0..=clamped_lines {
-        res.push_str(&str[i * max_len..std::cmp::min(str.len(), (i + 1) * max_len)]);
-        res.push('\n');
-    }
+    /// SPCs loop forever, so cut converted tracks to this length (mm:ss or seconds, counted after --trim-start)
+    #[arg(long, value_parser = parse_duration)]
+    duration: Option<f64>,
 
-    res
-}
+    /// Fade out over this long before --duration ends (mm:ss or seconds)
+    #[arg(long, value_parser = parse_duration, requires = "duration")]
+    fade: Option<f64>,
 
-/// Applies the provided character mapper, leaving as-is non-defined mappings.
-fn alphamap(str: &str, mapper: &HashMap<char, char>) -> String {
-    str
-        .chars()
-        .map(|c| mapper.get(&c).copied().unwrap_or(c))
-        .collect()
-}
+    /// Extra directory to look for spc2wav's output in (for builds that don't write beside the .spc)
+    #[arg(long)]
+    spc2wav_outdir: Option<PathBuf>,
 
-/// Downloads file at specified URL and updates provided indicatif bar. Specific to this project (s2w).
-fn s2w_download(url: &str, dest: &str, client: &reqwest::blocking::Client, size: u64) {
-    let resp = client.get(url).send().unwrap();
-    let mut reader = resp.bytes().unwrap();
-    let mut file = fs::File::create(dest).unwrap();
+    /// Just print the submission's (cleaned) description and exit; nothing is downloaded
+    #[arg(long, conflicts_with = "download_only")]
+    preview_description: bool,
 
-    let bar = ProgressBar::new(size);
-    bar.set_style(ProgressStyle::with_template("{bar:83} {percent:0}% ({bytes}/{total_bytes})")
-        .unwrap()
-        .progress_chars("█▒░"));
+    /// Only download + extract the .spc file(s); no rendering, conversion or tagging
+    #[arg(long, conflicts_with_all = ["format", "output", "from_wav", "peak_normalize_to"])]
+    download_only: bool,
 
+    /// How many tracks of a pack to render/convert at once
+    #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..=64))]
+    jobs: u16,
 
-    let mut dl_bytes = 0;
-    for chunk in reader.chunks(1024) {
-        file.write_all(&chunk).unwrap();
-        dl_bytes += chunk.len() as u64;
-        bar.set_position(dl_bytes);
-        sleep(Duration::from_millis(4)); // TODO: Most of these files are <50kb, so add a *very* tiny delay for user gratification! This theoretically shouldn't cause any problematic (artificial) waiting, but if making this function generic, stay wary of arbitrary dl size.
-    }
+    /// Resolve the query and print what would be done, without any HTTP requests or writes
+    #[arg(long)]
+    dry_run: bool,
 
-    bar.finish_and_clear();
-}
+    /// Always download the zip afresh, leaving the cache untouched
+    #[arg(long)]
+    no_cache: bool,
 
-/// Extracts files at provided location and updates indicatif bar. Specific to this project (s2w; only keeps .spc).
-fn s2w_extract(loc: &str) {
-    // Modified from "zip" crate example https://github.com/zip-rs/zip2/blob/7c20fa30016301909bf2ade203cb4841b7776154/examples/extract.rs
-
-    let archive_file = fs::File::open(loc).unwrap();
-    let mut archive = zip::ZipArchive::new(archive_file).unwrap();
-
-    let bar = ProgressBar::new(archive.len() as u64);
-    bar.set_style(ProgressStyle::with_template("{bar:83} {percent:0}% ({pos}/{len})")
-        .unwrap()
-        .progress_chars("█▒░"));
-    bar.tick();
-
-    for i in 0..archive.len() {
-        bar.inc(1);
-
-        let mut file = archive.by_index(i).unwrap();
-
-        // Validate path (skip pass if invalid)
-        let fpath = match file.enclosed_name() {
-            Some(path) => path,
-            None => continue
-        };
-
-        // Check file extension (skip pass if not .spc — this implicitly removes directories!)
-        if fpath.extension().and_then(|e| e.to_str()).is_some_and(|e| e == "spc") {
-            // Yank file to base directory and write file
-            let bpath = fpath.file_name().unwrap();
-            let mut outfile = fs::File::create(&bpath).unwrap();
-            io::copy(&mut file, &mut outfile).unwrap();
-        } else {
-            sleep(Duration::from_millis(20));
-        }
-    }
+    /// Render every track again, even one whose WAV an earlier run (e.g. --no-convert) left in the output directory
+    #[arg(long, conflicts_with_all = ["from_wav", "download_only", "list_tracks"])]
+    force: bool,
 
-    // Delete zip file
-    fs::remove_file(loc).unwrap();
+    /// Keep the archive's folder structure under the output directory instead of flattening it
+    #[arg(long)]
+    keep_dirs: bool,
 
-    bar.finish_and_clear();
-}
+    /// Tag even a single SPC as an album: track number plus its own title, the submission name becoming the album
+    #[arg(long, conflicts_with = "no_album_mode")]
+    album_mode: bool,
 
-/// Converts specified .spc file to .wav using spc2wav utility and updates indicatif bar. Specific to this project (s2w).
-fn s2w_conv(loc: &str) {
-    let bar = ProgressBar::new(1);
-    bar.set_style(ProgressStyle::with_template("{bar:83} {percent:0}% ({pos}/{len})")
-        .unwrap()
-        .progress_chars("█▒░"));
+    /// Treat a pack like a single track: convert only one of its SPCs (asked for, else the first)
+    #[arg(long, conflicts_with_all = ["download_only", "list_tracks", "from_wav"])]
+    no_album_mode: bool,
 
-    bar.tick();
+    /// Download the zip and list its .spc tracks (name + size), then stop; nothing is extracted or converted
+    #[arg(long, conflicts_with_all = ["download_only", "from_wav", "preview_description", "json"])]
+    list_tracks: bool,
 
-    Command::new("spc2wav")
-        .arg(loc)
-        .output()
-        .expect("Violation of: spc2wav could not be run (is it installed?)");
+    /// Keep spc2wav's .wav as the final output: no SoX step (and no prompt for one), still tagged
+    #[arg(long, conflicts_with_all = ["format", "output", "download_only", "peak_normalize_to", "trim_start", "rate", "bits", "dither", "channels", "duration"])]
+    no_convert: bool,
 
-    fs::remove_file(loc).expect("Could not delete .spc file");
+    /// Convert + tag an existing WAV (e.g. from a prior run) instead of downloading/rendering again
+    #[arg(long, default_missing_value = None)]
+    from_wav: Option<PathBuf>,
 
-    bar.inc(1);
-    sleep(Duration::from_millis(10));
-    bar.finish_and_clear();
-}
+    /// Transliterate derived output filenames to plain ASCII (for FAT32/old players); tags stay unicode
+    #[arg(long)]
+    ascii_filenames: bool,
 
-/// Overwrites previous printed line (assuming println) with text and flushes stdout. Use format macro for stringf.
-fn ow_printl(str: &str, lines: usize) {
-    println!("{}\x1B[2K{}{}", "\x1B[A".repeat(lines), str, "\x1B[B".repeat(lines-1));
-    io::stdout().flush().unwrap();
-}
+    /// Reveal the output location in the system file manager once done
+    #[arg(long)]
+    open_folder: bool,
 
-fn ow_print(str: &str) {
-    ow_printl(str, 1);
-}
+    /// Stylization applied to displayed text
+    #[arg(long, value_enum, default_value_t = Style::Plain)]
+    style: Style,
 
-/// Converts byte(s) to hex representation in string.
-fn hex_str(bytes: &[u8], delim: &str) -> String {
-    bytes.iter()
-        .map(|b| format!("{:02x}", b))
-        .collect::<Vec<String>>()
-        .join(delim)
-}
+    /// Shorthand for --style tinycaps
+    #[arg(long, conflicts_with = "style")]
+    tiny_caps: bool,
+
+    /// How a pack track named only by its number ("03.spc") is titled: raw "03", stripped "3" or ordinal "3rd"
+    #[arg(long, value_enum, default_value_t = TrackTitleStyle::Stripped)]
+    track_title_style: TrackTitleStyle,
+
+    /// Set the output's modification time to the submission time rather than now
+    #[arg(long)]
+    preserve_time: bool,
+
+    /// Hide progress bars while keeping banners/status lines (default when stdout isn't a terminal)
+    #[arg(long)]
+    no_progress: bool,
+
+    /// Only print what matters (errors, warnings, saved paths): no banner, progress bars or step lines, no download confirmation
+    #[arg(long)]
+    quiet: bool,
+
+    /// Print one JSON object per query (submission metadata + output paths) instead of the human output; never prompts
+    #[arg(long, conflicts_with_all = ["quiet", "preview_description"])]
+    json: bool,
+
+    /// Batch: hide each item's banner/progress and print one aligned table (or, with --json, one JSON array) at the end
+    #[arg(long, requires = "file", conflicts_with = "quiet")]
+    summary_only: bool,
+
+    /// Skip the small cosmetic pauses between download/extract/convert steps (handy for batches)
+    #[arg(long)]
+    no_delay: bool,
+
+    /// Draw the UI with plain ASCII only (no box-drawing, stars, arrows…) for terminals/fonts that mangle them
+    #[arg(long, global = true)]
+    ascii_ui: bool,
+
+    /// Use this sox binary instead of whichever is on PATH
+    #[arg(long, env = "SMWC2WAV_SOX")]
+    sox_path: Option<PathBuf>,
+
+    /// Use this ffmpeg binary instead of whichever is on PATH
+    #[arg(long, env = "SMWC2WAV_FFMPEG")]
+    ffmpeg_path: Option<PathBuf>,
 
-/// Get filetype by magic number.
-/// Note standards may change, # not present, etc.
-fn magictype(data: &Vec<u8>) -> Option<FileType> {
-    let data_str = hex_str(data, ";").to_uppercase();
+    /// Use this spc2wav binary instead of whichever is on PATH
+    #[arg(long, env = "SMWC2WAV_SPC2WAV")]
+    spc2wav_path: Option<PathBuf>,
 
-    FileType::iter().find(|f| is_regex!(&data_str, f.magic()))
+    /// DANGER: skip TLS certificate validation (only for TLS-intercepting proxies)
+    #[arg(long)]
+    allow_insecure: bool,
+
+    /// Retry failed SMWC requests this many times (connection errors, timeouts, 5xx only), backing off each time
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(0..=10))]
+    retries: u32,
+
+    /// Tee timestamped logs (incl. subprocess commands/outputs) to a file
+    #[arg(long, default_missing_value = None)]
+    log_file: Option<PathBuf>,
+
+    /// Append to an existing --log-file rather than rotating it to <log-file>.old
+    #[arg(long)]
+    log_append: bool,
 }
 
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Dump the parsed SPC header and ID666 tag of a local .spc file
+    Probe {
+        file: PathBuf,
+
+        /// Emit as JSON rather than the human-readable listing
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Identify local files (or the files in a directory) by magic number
+    Identify {
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Also print the first 16 bytes (hex) of files that couldn't be identified
+        #[arg(long)]
+        report_unidentified: bool,
+
+        /// Emit as JSON rather than the human-readable listing
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Delete the downloaded zips (and their metadata) cached between runs
+    ClearCache,
+
+    /// List the submissions cached between runs (name, when cached, metadata/zip sizes)
+    ListCached {
+        /// Only show these submission IDs
+        ids: Vec<u16>,
+
+        /// Emit as JSON rather than the human-readable listing
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show one cached submission in detail: where its metadata and zip are stored, their sizes and when
+    CacheInfo {
+        id: u16,
+
+        /// Emit as JSON rather than the human-readable listing
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print the JSON Schema of the API response and --json output structures
+    #[command(hide = true)]
+    PrintSchema,
+}
 
 fn main() {
     // Arguments...
     // smwc2wav — CLI browser for SMWCentral "Music" section + searching.
-    // smwc2wav -f [query file] — pass in a file with queries desired (plaintext, delimited by CRLF).
+    // smwc2wav -F [query file] — pass in a file with queries desired (plaintext, delimited by CRLF).
     // smwc2wav -q [URL or ID] — shorthand catch-all -i, -u, -f
 
     // smwc2wav -i [ID]
@@ -370,146 +322,278 @@ fn main() {
 
 
 
-    let client = reqwest::blocking::Client::new();
     let args = Cli::parse();
+    set_ascii_ui(args.ascii_ui);
+
+    // Older Windows consoles print the colour/cursor escapes literally unless asked not to
+    #[cfg(windows)]
+    let _ = enable_ansi_support::enable_ansi_support();
+    let log = RunLog::open(args.log_file.as_deref(), args.log_append).unwrap_or_else(|e| {
+        eprintln!("{}", ui(&format!("\x1B[38;2;255;80;80m✘ {:#}\x1B[0m", e)));
+        std::process::exit(1);
+    });
+    log.log(&format!("smwc2wav v{} started with {:#?}", env!("CARGO_PKG_VERSION"), args));
+
+    // One readable line instead of a backtrace; the full chain is in it ("a: b: c")
+    if let Err(e) = s2w_main(args, &log) {
+        log.log(&format!("ERROR {:#}", e));
+        eprintln!("{}", ui(&format!("\x1B[38;2;255;80;80m✘ {:#}\x1B[0m", e)));
+        std::process::exit(1);
+    }
+}
+
+/// Runs everything after argument parsing. Specific to this project (s2w).
+fn s2w_main(args: Cli, log: &RunLog) -> anyhow::Result<()> {
+    if args.allow_insecure {
+        eprintln!("{}", ui("\x1B[38;2;255;80;80m⚠ --allow-insecure: TLS certificates are NOT being validated. Anyone on the network path can tamper with downloads!\x1B[0m"));
+        log.log("TLS certificate validation disabled (--allow-insecure)");
+    }
 
+    set_tool_paths(&[("sox", args.sox_path.as_deref()), ("ffmpeg", args.ffmpeg_path.as_deref()), ("spc2wav", args.spc2wav_path.as_deref())], log)?;
+
+    let client = reqwest::blocking::Client::builder()
+        .danger_accept_invalid_certs(args.allow_insecure)
+        .build()
+        .context("HTTP client could not be built")?;
+
+    // Animated bars just spam non-TTY logs
+    let suppress_output = args.quiet || args.json || args.summary_only;
+    let no_progress = args.no_progress || suppress_output || !io::stdout().is_terminal();
+    set_plain_status(no_progress);
+
+    if let Some(command) = &args.command {
+        match command {
+            Commands::Probe { file, json } => s2w_probe(file, *json)?,
+            Commands::Identify { paths, report_unidentified, json } => s2w_identify(paths, *report_unidentified, *json)?,
+            Commands::ClearCache => s2w_clear_cache()?,
+            Commands::ListCached { ids, json } => s2w_list_cached(ids, *json)?,
+            Commands::CacheInfo { id, json } => s2w_cache_info(*id, *json)?,
+            Commands::PrintSchema => print_json(&json_schemas()),
+        }
+        return Ok(());
+    }
+
+    // Fail the cases that can't get anywhere before anything else; scripts get the usage rather than a prompt that hangs
+    let is_tty = io::stdin().is_terminal() && io::stdout().is_terminal();
+    if args.query.is_none() && args.file.is_none() && !is_tty {
+        if args.search.is_some() {
+            bail!("--search needs a terminal to pick a result from; pass -q with the ID instead");
+        }
+        let _ = Cli::command().print_help();
+        std::process::exit(2);
+    }
 
     // Validate arguments first for the sake of not hitting the user with a panic 3 minutes into operation
-    let (is_skip, is_format_valid) = match &args.format {
+    let needs_spc2wav = !(args.from_wav.is_some() || args.download_only || args.list_tracks || args.preview_description || args.dry_run);
+    if needs_spc2wav && !has_tool("spc2wav") {
+        bail!("spc2wav not found! Install it onto PATH or point --spc2wav-path at it (or use --download-only to just fetch the .spc files)");
+    }
+
+    let out_format = args.output.as_deref().map(output_format).transpose()?;
+
+    let format = match (out_format, &args.format) {
+        (Some(o), Some(f)) if !o.eq_ignore_ascii_case(f) => bail!("--output (.{}) and --format ({}) disagree!", o.replace("skip", "wav"), f),
+        (Some(o), _) => Some(o),
+        (None, f) => f.clone(),
+    };
+
+    // No format given: ask on a terminal, otherwise keep the WAV
+    let is_interactive = !args.json && is_tty;
+    let format = format.map(|f| f.to_lowercase());
+    let (is_skip, is_format_valid) = match &format {
+        None => (args.no_convert || !is_interactive, false),
         Some(f) if f == "skip" => (true, false),
-        Some(f) if is_regex!(f, "(?i)(flac|mp3|aiff|ogg)") => (false, true),
-        _ => panic!("Invalid conversion format! Must be flac/mp3/aiff/ogg"),
+        Some(f) if FFMPEG_FORMATS.contains(&f.as_str()) => (false, true),
+        _ => bail!("Invalid conversion format! Must be {} ({} need ffmpeg)", SOX_FORMATS.join("/"), FFMPEG_FORMATS[SOX_FORMATS.len()..].join("/")),
     };
 
-    let ca_data: Option<(Vec<u8>, MimeType)> = if let Some(ca) = args.coverart {
-        let ca_file = fs::read(ca).expect("Cover art image could not be read");
-        let ca_meta = magictype(&ca_file).expect("Cover art file could not be identified");
-        let ca_mime = ca_meta.mime();
+    // Batches run unattended, so the format prompt must never come up mid-way
+    if args.file.is_some() && format.is_none() && !args.no_convert {
+        bail!("-F/--file needs --format (or --no-convert) since batch mode never prompts!");
+    }
+
+    if args.peak_normalize_to.is_some_and(|p| p > 0.0 || p.is_nan()) {
+        bail!("Invalid --peak-normalize-to! Must be ≤ 0 dBFS");
+    }
 
-        if ca_mime.is_err() {
-            panic!("Improper MIME type!");
+    if let Some(t) = args.trim_start {
+        if !(t.is_finite() && t > 0.0) {
+            bail!("Invalid --trim-start! Must be a positive number of seconds");
         }
+        if !is_format_valid {
+            bail!("--trim-start needs a target format via --format or --output!");
+        }
+    }
 
-        Some((ca_file, ca_mime.unwrap()))
-    } else {
-        None
-    };
+    if args.genre.as_deref().is_some_and(|g| g.trim().is_empty()) {
+        bail!("Invalid --genre! Must not be blank");
+    }
+    let tag_opts = TagOpts { album: args.album.clone(), genre: args.genre.clone(), year: args.year, is_multi_artist: args.multi_artist, is_stats: args.embed_stats };
 
-    let id = {
-        let pat = regex!("[0-9]*$");
-        pat.find(&args.query).unwrap().as_str()
-    };
-    let smwc_api = Url::parse(&format!("https://www.smwcentral.net/ajax.php?a=getfile&v=2&id={}", id)).expect("Violation of: invalid SMWc API URL!");
-    let api_resp = client.get(smwc_api).send().unwrap();
-    let file: SMWCFile = api_resp.json().expect("Could not connect to SMWCentral or ID invalid.");
+    let audio_opts = AudioOpts { rate: args.rate, bits: args.bits, dither: args.dither, channels: args.channels, duration: args.duration, fade: args.fade };
+    if audio_opts.rate.is_some_and(|r| !(8000..=192000).contains(&r)) {
+        bail!("Invalid --rate! Must be 8000–192000 Hz");
+    }
+    if audio_opts.bits.is_some_and(|b| b != 16 && b != 24) {
+        bail!("Invalid --bits! Must be 16 or 24");
+    }
+    if audio_opts.channels.is_some_and(|c| c != 1 && c != 2) {
+        bail!("Invalid --channels! Must be 1 (mono) or 2 (stereo)");
+    }
+    if audio_opts.duration.is_some_and(|d| d <= 0.0) {
+        bail!("Invalid --duration! Must be longer than 0 seconds");
+    }
+    if let (Some(d), Some(f)) = (audio_opts.duration, audio_opts.fade) {
+        if f > d {
+            bail!("--fade ({}s) can't be longer than --duration ({}s)!", f, d);
+        }
+    }
+    if audio_opts.rate.is_some() || audio_opts.bits.is_some() || audio_opts.channels.is_some() || audio_opts.duration.is_some() {
+        if !is_format_valid {
+            bail!("--rate/--bits/--channels/--duration need a target format via --format or --output!");
+        }
+        if audio_opts.bits.is_some() && !matches!(format.as_deref(), Some("flac" | "aiff")) {
+            bail!("--bits only applies to lossless flac/aiff output!");
+        }
+    }
 
-    let is_obsolete = file.obsoleted_by.is_some();
-    let is_featured = file.raw_fields.featured;
+    if let Some(wav) = &args.from_wav {
+        if !is_format_valid {
+            bail!("--from-wav needs a target format via --format or --output!");
+        }
 
-    let hrtime = unix_to_hrtime(file.time);
-    //let alphamapper = alphavec_to_map(TINY_CAPS_MAPPING);
+        let wav_type = magictype_from_path(wav).with_context(|| format!("WAV file {} could not be read", wav.display()))?;
+        if wav_type != Some(FileType::WAV) {
+            bail!("{} is not a WAV file!", wav.display());
+        }
+    }
 
-    println!("\x1B[38;2;131;125;246m\n▓▓▓▓▓▓▓▓▓▒▓▓▓▓▒▒▒▒▒▒▓▓▒▒▓▒▒▒▒▒░▒▒▒▒▒▒▒▒░▒▒░░▒▒▒▒▒░░░▒▒░▒░▒▒░░▒▒▒▒▒░▒▒░░░░▒▒▒░░░░░▒░▒░░░░░▒░░░░░▒░░░░░░░░░░░░░\n");
+    let base_cover = args.coverart.as_deref().map(load_cover).transpose()?;
+
+    // Only now go out to the network (search/browse), with the arguments known to be good; no query → browse interactively
+    let queries: Vec<String> = match (&args.query, &args.file) {
+        (None, None) if args.search.is_some() => match s2w_search(args.search.as_deref().unwrap(), args.limit.map(|l| l as usize), args.page.unwrap_or(1), &client, args.retries, log)? {
+            Some(id) => vec![id],
+            None => return Ok(()),
+        },
+        (_, Some(batch)) => fs::read_to_string(batch)
+            .with_context(|| format!("Could not read query file {}", batch.display()))?
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(str::to_string)
+            .collect(),
+        (Some(q), None) => vec![q.clone()],
+        (None, None) => match s2w_browse(&client, args.retries, log)? {
+            Some(id) => vec![id],
+            None => return Ok(()),
+        },
+    };
 
-    println!(" ╔{}╗", "═".repeat(file.name.len() + 2 + is_featured.then_some(2).unwrap_or(0)));
-    print!(" ║ {}", file.name);
-    if is_featured { println!(" * ║") } else { println!("  ║") }
-    println!(" ╚{}╝", "═".repeat(file.name.len() + 2 + is_featured.then_some(2).unwrap_or(0)));
+    // A lone bad ID fails before anything else happens; in a batch it's just one failed item
+    if args.file.is_none() {
+        smwc_id(&queries[0]).with_context(|| format!("could not parse SMWC ID from query \"{}\"", queries[0]))?;
+    }
 
-    print!("  └── {} ——— {} ——— {}", file.raw_fields.duration, HumanBytes(file.size as u64), file.authors.iter().map(|a| a.name.clone()).collect::<Vec<String>>().join(", "));
-    match file.submitter {
-        Some(s) => println!("[{}]", s.name),
-        None => println!()
+    // Nobody is there to press enter when piped; batches shouldn't stop per item either
+    let is_yes = args.yes || suppress_output || args.file.is_some() || !io::stdin().is_terminal();
+    if !(args.yes || suppress_output || args.file.is_some() || args.from_wav.is_some() || args.dry_run) && is_yes {
+        eprintln!("{}", ui("⚠ stdin isn't a terminal; downloading without confirmation (as if --yes)"));
     }
 
-    println!("     │\n     └── @ {}-{}-{} {}:{}:{}", hrtime.month(), hrtime.day(), hrtime.year(), hrtime.hour(), hrtime.minute(), hrtime.second());
+    let opts = RunOpts {
+        output: args.output.clone(),
+        output_dir: args.output_dir.clone(),
+        from_wav: args.from_wav.clone(),
+        spc2wav_outdir: args.spc2wav_outdir.clone(),
+        format,
+        is_skip,
+        is_format_valid,
+        is_interactive,
+        is_yes,
+        suppress_output,
+        quiet: args.quiet,
+        json: args.json,
+        no_progress,
+        no_delay: args.no_delay,
+        dry_run: args.dry_run,
+        download_only: args.download_only,
+        list_tracks: args.list_tracks,
+        preview_description: args.preview_description,
+        no_convert: args.no_convert,
+        no_cache: args.no_cache,
+        force: args.force,
+        keep_dirs: args.keep_dirs,
+        ascii_filenames: args.ascii_filenames,
+        open_folder: args.open_folder,
+        preserve_time: args.preserve_time,
+        auto_cover: args.auto_cover,
+        dedup_authors: args.dedup_authors,
+        coverart: args.coverart.clone(),
+        cover: base_cover,
+        style: if args.tiny_caps { Style::TinyCaps } else { args.style },
+        track_title_style: args.track_title_style,
+        album_mode: match (args.album_mode, args.no_album_mode) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        },
+        require_tag: args.require_tag.clone(),
+        exclude_tag: args.exclude_tag.clone(),
+        keep_ext: args.keep_ext.clone(),
+        trim_start: args.trim_start,
+        peak_normalize_to: args.peak_normalize_to,
+        audio: audio_opts,
+        tags: tag_opts,
+        jobs: args.jobs as usize,
+        retries: args.retries,
+    };
 
-    match file.rating {
-        Some(r) => print!("        │\n        └── {}", q_str(&'★', &'☆', r as u8, 5)),
-        None => print!("        │\n        └── (no rating)")
+    // Everything from here on is per query
+    let s2w_query = |query: &str| s2w_run(query, &opts, &client, log);
+
+    // A failed step leaves its phase line up; end it there so nothing after gets drawn over
+    if args.file.is_none() {
+        let res = s2w_query(&queries[0]);
+        ow_end();
+        match (args.json, res?) {
+            (true, RunOutcome::Saved(result)) => print_json(&result),
+            (true, RunOutcome::Planned(plan)) => print_json(&plan),
+            _ => {}
+        }
+        return Ok(());
     }
-    println!(" {} downloads\n\n", file.downloads);
-
-    println!("tags ▶  {}", file.tags.join(", "));
-    println!("source ▶  {}", file.raw_fields.source);
-    println!("samples ▶  {}\n\n\n\n", file.raw_fields.samples);
-    println!("⏷  info  ⏷\n\n{}", strclamp(&strip_html(&file.raw_fields.description), 100));
-    println!("{}", "\n▓▓▓▓▓▓▓▓▓▒▓▓▓▓▒▒▒▒▒▒▓▓▒▒▓▒▒▒▒▒░▒▒▒▒▒▒▒▒░▒▒░░▒▒▒▒▒░░░▒▒░▒░▒▒░░▒▒▒▒▒░▒▒░░░░▒▒▒░░░░░▒░▒░░░░░▒░░░░░▒░░░░░░░░░░░░░\x1B[0m\n");
-
-    print!("Confirm download...");
-    io::stdout().flush().unwrap();
-    io::stdin().read_line(&mut String::new()).unwrap();
-    ow_print("Downloading zip (1/3)");
-    let zip_fname = file.id.to_string() + ".zip";
-    s2w_download(&*file.download_url, &*zip_fname, &client, file.size as u64);
-
-    ow_print("Extracting zip (2/3)");
-    io::stdout().flush().unwrap();
-    s2w_extract(&zip_fname);
-
-    ow_print("Converting spc → wav (3/3)");
-    let all_files = fs::read_dir(".").unwrap();
-    let spc_files: Vec<_> = all_files
-        .filter_map(Result::ok)
-        .filter(|e| {
-            e.path().extension().map_or(false, |ext| ext == "spc")
-        })
-        .collect();
-
-    let spc_fname = spc_files[0].file_name();
-    let spc_name = spc_fname.to_str().unwrap();
-    s2w_conv(spc_name);
-
-    let wav_name = &spc_name.replace(".spc", ".wav");
-    let wav_meta = fs::metadata(wav_name).unwrap();
-    ow_print(&format!("\x1B[38;2;41;255;188m{} of 16-bit goodness saved ✔\x1B[0m", HumanBytes(wav_meta.size())));
-
-    let has_sox: bool = which("sox").unwrap().exists();
-    let has_ffmpeg: bool = which("ffmpeg").unwrap().exists();
-
-    if !is_skip {
-        let is_conv = Confirm::new("SoX detected. Convert audio format?").prompt();
-
-        if has_sox && is_conv.expect("No choice!") {
-            let conv_format: &str = if is_format_valid {
-                &*args.format.unwrap()
-            } else {
-                Select::new("Select format:", vec!["flac", "mp3", "aiff", "ogg"]).prompt().unwrap()
-            };
-
-            let conv_name = &wav_name.replace(".wav", &format!(".{}", conv_format));
-            ow_printl("\x1B[38;2;143;122;238mProcessing via SoX...\x1B[0m", 3);
-
-            Command::new("sox")
-                .arg(wav_name)
-                .arg(conv_name)
-                .output()
-                .expect("SoX failed to convert file.");
-
-            ow_printl(&format!("\x1B[38;2;41;255;188m{} → {} converted ✔\x1B[0m", HumanBytes(wav_meta.size()), HumanBytes(fs::metadata(conv_name).unwrap().size())), 3);
-
-            fs::remove_file(wav_name).expect("Could not remove .wav file");
-
-            let mut tag = Tag::default().read_from_path(conv_name).unwrap();
-            tag.set_title(&*file.name);
-            tag.set_artist(&*file.authors.iter()
-                .map(|a| a.name.clone())
-                .collect::<Vec<String>>()
-                .join(", "));
-
-            if let Some(album) = args.album {
-                tag.set_album(Album::with_title(&album));
-            }
-
-            if let Some((ca_file, ca_mime)) = ca_data {
-                tag.set_album_cover(Picture::new(&*ca_file, ca_mime));
-            }
-
-            tag.set_year(hrtime.year());
-            tag.set_comment("Processed by smwc2wav".into());
-            tag.set_genre("Game");
-
-            tag.write_to_path(conv_name).expect("Failed to save ID3 tags");
+
+    // Batch: keep going past failures, then sum up
+    let reports = s2w_batch(&queries, &opts, args.json && !args.summary_only, log, s2w_query);
+
+    // Keep stdout pure JSON lines under --json
+    let failed: Vec<&RunReport> = reports.iter().filter(|r| matches!(r.status, RunStatus::Failed | RunStatus::Panicked)).collect();
+    let panicked = failed.iter().filter(|r| r.status == RunStatus::Panicked).count();
+    let mut summary = format!("\n{} succeeded, {} failed", queries.len() - failed.len(), failed.len() - panicked);
+    if panicked > 0 {
+        summary.push_str(&format!(", {} panicked", panicked));
+    }
+    if args.summary_only && !args.json {
+        summary = format!("\n{}{}", summary_table(&reports), summary);
+    } else {
+        for r in &failed {
+            let why = if r.status == RunStatus::Panicked { " (panicked)" } else { "" };
+            summary.push_str(&format!("\n  ✘ {}{}", r.query, why));
         }
     }
-}
\ No newline at end of file
+    if args.json {
+        if args.summary_only {
+            print_json_line(&reports);
+        }
+        eprintln!("{}", ui(&summary));
+    } else {
+        println!("{}", ui(&summary));
+    }
+
+    if !failed.is_empty() {
+        bail!("{} of {} queries failed", failed.len(), queries.len());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+//! Runs the built binary for the behaviour that only shows at the CLI level (exit codes, stdout purity).
+
+use std::process::{Command, Output, Stdio};
+
+fn smwc2wav(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_smwc2wav"))
+        .args(args)
+        .env_remove("SMWC2WAV_SPC2WAV")
+        .stdin(Stdio::null())
+        .output()
+        .expect("smwc2wav could not be run")
+}
+
+#[test]
+fn no_args_off_a_terminal_prints_usage() {
+    let out = smwc2wav(&[]);
+
+    assert_eq!(out.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&out.stdout).starts_with("Usage: smwc2wav"));
+    assert!(out.stderr.is_empty());
+}
+
+#[test]
+fn search_off_a_terminal_fails_before_any_request() {
+    // An unresolvable proxy makes any request fail loudly instead of quietly reaching SMWC
+    let out = Command::new(env!("CARGO_BIN_EXE_smwc2wav"))
+        .args(["--search", "kirby"])
+        .env("HTTPS_PROXY", "http://invalid.invalid:1")
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+
+    assert_eq!(out.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("--search needs a terminal"));
+}
+
+#[test]
+fn log_file_records_the_run_and_rotates() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("run.log");
+    let log_arg = log.to_str().unwrap();
+
+    for _ in 0..2 {
+        assert!(smwc2wav(&["--log-file", log_arg, "-q", "1", "--dry-run"]).status.success());
+    }
+
+    let text = std::fs::read_to_string(&log).unwrap();
+    let stamped: Vec<&str> = text.lines().filter(|l| l.starts_with('[')).collect();
+    assert!(stamped[0].contains("] smwc2wav v") && stamped[0].ends_with("started with Cli {"));
+    assert!(text.contains("dry_run: true"));
+    assert_eq!(stamped.last().unwrap().split_once("] ").unwrap().1, "Dry run for #1");
+    for line in &stamped {
+        let stamp = &line[1..20];
+        assert!(chrono::NaiveDateTime::parse_from_str(stamp, "%Y-%m-%d %H:%M:%S").is_ok(), "bad timestamp in {:?}", line);
+    }
+    assert!(dir.path().join("run.log.old").is_file());
+}
+
+#[test]
+fn from_wav_rejects_a_non_wav_before_any_request() {
+    let dir = tempfile::tempdir().unwrap();
+    let fake = dir.path().join("track.wav");
+    std::fs::write(&fake, b"ID3 not really a wav").unwrap();
+
+    let out = smwc2wav(&["-q", "1", "-f", "flac", "--from-wav", fake.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("is not a WAV file!"));
+}
+
+/// Stdout of a --json run, checked to be nothing but UTF-8 JSON text ending in a newline.
+fn json_stdout(out: &Output) -> &str {
+    assert!(out.status.success(), "failed: {}", String::from_utf8_lossy(&out.stderr));
+    let text = std::str::from_utf8(&out.stdout).expect("stdout is not UTF-8");
+    assert!(text.ends_with('\n') && !text.contains('\x1B'));
+    text
+}
+
+#[test]
+fn json_stdout_is_only_json() {
+    let dir = tempfile::tempdir().unwrap();
+    let queries = dir.path().join("queries.txt");
+    std::fs::write(&queries, "1\nhttps://www.smwcentral.net/?p=section&a=details&id=2\n").unwrap();
+    std::fs::write(dir.path().join("blob.bin"), [0xAB, 0xCD]).unwrap();
+
+    let plan: serde_json::Value = serde_json::from_str(json_stdout(&smwc2wav(&["-q", "1", "--dry-run", "--json"]))).unwrap();
+    assert_eq!(plan["id"], 1);
+
+    let batch = smwc2wav(&["-F", queries.to_str().unwrap(), "-f", "flac", "--dry-run", "--json"]);
+    let lines: Vec<serde_json::Value> = json_stdout(&batch).lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(lines.iter().map(|l| l["id"].as_u64().unwrap()).collect::<Vec<_>>(), [1, 2]);
+
+    let summary = smwc2wav(&["-F", queries.to_str().unwrap(), "-f", "flac", "--dry-run", "--json", "--summary-only"]);
+    let reports: serde_json::Value = serde_json::from_str(json_stdout(&summary)).unwrap();
+    assert_eq!(reports.as_array().unwrap().len(), 2);
+
+    let found: serde_json::Value = serde_json::from_str(json_stdout(&smwc2wav(&["identify", "--report-unidentified", "--json", dir.path().to_str().unwrap()]))).unwrap();
+    assert_eq!(found.as_array().unwrap().len(), 2);
+}